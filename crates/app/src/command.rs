@@ -0,0 +1,152 @@
+//! 游戏启动命令
+
+use std::fs;
+use std::process::Command;
+
+use mc::MCInstallation;
+use utils::shell::quote;
+
+use crate::{LauncherError, version::ConfigMC};
+
+/// 常见的Linux封装器
+const KNOWN_WRAPPERS: [&str; 8] = [
+    "gamemoderun",
+    "mangohud",
+    "prime-run",
+    "pvkrun",
+    "optirun",
+    "primusrun",
+    "gamescope",
+    "obs-gamecapture",
+];
+
+/// 隐去access_token时，脚本从该环境变量读取
+const TOKEN_ENV: &str = "CEMCL_ACCESS_TOKEN";
+
+/// 完整的游戏启动命令，启动游戏和导出脚本共用
+pub struct GameCommand {
+    /// 封装器、java及其参数
+    pub args: Vec<String>,
+    /// 额外的环境变量
+    pub env: Vec<(String, String)>,
+    /// 工作目录
+    pub work_dir: String,
+}
+
+impl GameCommand {
+    /// cmd_list: mc::launch::get_launch_command获取的参数
+    pub fn new(
+        version: &MCInstallation,
+        config: &ConfigMC,
+        cmd_list: Vec<String>,
+    ) -> Result<Self, LauncherError> {
+        // 前面的封装器以后面的封装器及java为参数
+        let mut args: Vec<String> = version
+            .wrapper
+            .iter()
+            .filter(|wrapper| !wrapper.is_empty())
+            .flatten()
+            .cloned()
+            .collect();
+        args.push(version.java_path.clone());
+        args.extend(cmd_list);
+
+        // 游戏参数中的路径相对于启动器目录
+        let work_dir = std::env::current_dir()?.to_string_lossy().to_string();
+
+        // 版本中的同名变量覆盖全局变量
+        let mut env = config.env.clone();
+        env.extend(version.env.clone());
+
+        Ok(Self {
+            args,
+            env: env.into_iter().collect(),
+            work_dir,
+        })
+    }
+
+    pub fn to_command(&self) -> Command {
+        let mut cmd = Command::new(&self.args[0]);
+        cmd.args(&self.args[1..])
+            .envs(self.env.clone())
+            .current_dir(&self.work_dir);
+        cmd
+    }
+
+    /// 生成POSIX shell脚本，redacted_token不为None时，脚本中的该值会替换为环境变量
+    pub fn to_shell_script(&self, redacted_token: Option<&str>) -> String {
+        // 离线账号的token为空或不在参数中，此时不需要环境变量
+        let token = redacted_token
+            .filter(|token| !token.is_empty() && self.args.iter().any(|arg| arg.contains(token)));
+
+        let mut script = String::from("#!/bin/sh\n");
+        script += &format!(
+            "# Generated by CE Minecraft Launcher {}\n",
+            env!("CARGO_PKG_VERSION")
+        );
+
+        if token.is_some() {
+            script += &format!(
+                "# The access token is redacted, please set {TOKEN_ENV} before running.\n"
+            );
+            script += &format!(": \"${{{TOKEN_ENV}:?{TOKEN_ENV} is not set}}\"\n");
+        }
+        script += &format!("cd {} || exit 1\n", quote(&self.work_dir));
+
+        for (k, v) in &self.env {
+            script += &format!("export {k}={}\n", quote(v));
+        }
+
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|arg| match token {
+                Some(token) => quote_redacted(arg, token),
+                None => quote(arg),
+            })
+            .collect();
+        script += &format!("exec {}\n", args.join(" "));
+
+        script
+    }
+}
+
+/// 在PATH中查找常见的封装器
+pub fn detect_wrappers() -> Vec<String> {
+    KNOWN_WRAPPERS
+        .iter()
+        .filter(|name| utils::find_in_path(name).is_some())
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// 为参数加引号，并将其中的token替换为环境变量
+fn quote_redacted(arg: &str, token: &str) -> String {
+    if token.is_empty() || !arg.contains(token) {
+        return quote(arg);
+    }
+
+    arg.split(token)
+        .map(|s| {
+            if s.is_empty() {
+                String::new()
+            } else {
+                quote(s)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(&format!("\"${TOKEN_ENV}\""))
+}
+
+/// 写入脚本并添加可执行权限
+pub fn write_script(path: &str, content: &str) -> Result<(), LauncherError> {
+    fs::write(path, content)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    }
+
+    Ok(())
+}
@@ -2,8 +2,10 @@
 use std::io::ErrorKind;
 
 use downloader::DownloadManagerError;
+use java::adoptium::AdoptiumError;
+use java::runtime::RuntimeError;
 use mc::account::auth::AuthError;
-use mc::{DownloadError, launch::LaunchError};
+use mc::{DownloadError, forge::InstallError, launch::LaunchError};
 
 #[derive(Debug)]
 pub enum LauncherError {
@@ -31,8 +33,24 @@ pub enum LauncherError {
     FileNotFound,
     /// game config.json invalid
     GameConfigError,
+    /// Game directory is in use
+    GameRunning,
+    /// Hook command failed
+    HookFailed(String),
+    /// Failed to install a mod loader
+    InstallFailed(String),
     /// Operation interrupted
     Interrupted,
+    /// Not a Java installation
+    InvalidJava(String),
+    /// Invalid xms or xmx
+    InvalidMemory(String),
+    /// java.json invalid
+    JavaConfigError,
+    /// No Java for the required major version
+    JavaNotFound(u8),
+    /// Failed to install a Java runtime
+    JavaRuntimeError(String),
     /// launcher config.json invalid
     LauncherConfigError,
     /// Login data invalid
@@ -53,6 +71,8 @@ pub enum LauncherError {
     SendError,
     /// Semaphore Error
     SemaphoreError(String),
+    /// stats.json invalid
+    StatsConfigError,
     /// Task Set Not Found
     TaskSetNotFound,
     /// Weak pointer upgrade error
@@ -118,6 +138,38 @@ impl From<DownloadError> for LauncherError {
     }
 }
 
+impl From<AdoptiumError> for LauncherError {
+    fn from(value: AdoptiumError) -> Self {
+        match value {
+            AdoptiumError::DeserializeError(err) => err.into(),
+            AdoptiumError::IOError(err) => err.into(),
+            AdoptiumError::ReqwestError(err) => err.into(),
+            _ => LauncherError::JavaRuntimeError(value.to_string()),
+        }
+    }
+}
+
+impl From<InstallError> for LauncherError {
+    fn from(value: InstallError) -> Self {
+        match value {
+            InstallError::DeserializeError(err) => err.into(),
+            InstallError::IOError(err) => err.into(),
+            _ => LauncherError::InstallFailed(value.to_string()),
+        }
+    }
+}
+
+impl From<RuntimeError> for LauncherError {
+    fn from(value: RuntimeError) -> Self {
+        match value {
+            RuntimeError::DeserializeError(err) => err.into(),
+            RuntimeError::IOError(err) => err.into(),
+            RuntimeError::ReqwestError(err) => err.into(),
+            _ => LauncherError::JavaRuntimeError(value.to_string()),
+        }
+    }
+}
+
 impl From<LaunchError> for LauncherError {
     fn from(value: LaunchError) -> Self {
         match value {
@@ -190,7 +242,15 @@ impl std::fmt::Display for LauncherError {
             LauncherError::FileBusy => write!(f, "File is busy"),
             LauncherError::FileNotFound => write!(f, "File not found"),
             LauncherError::GameConfigError => write!(f, "Game config error"),
+            LauncherError::GameRunning => write!(f, "A game is already running in this directory"),
+            LauncherError::HookFailed(s) => write!(f, "Hook command failed: {s}"),
+            LauncherError::InstallFailed(s) => write!(f, "Install failed. {s}"),
             LauncherError::Interrupted => write!(f, "Operation interrupted"),
+            LauncherError::InvalidJava(s) => write!(f, "No Java installation in {s}"),
+            LauncherError::InvalidMemory(s) => write!(f, "Invalid memory settings: {s}"),
+            LauncherError::JavaConfigError => write!(f, "Java config error"),
+            LauncherError::JavaNotFound(v) => write!(f, "No compatible Java, Java {v} is required"),
+            LauncherError::JavaRuntimeError(s) => write!(f, "Java runtime error. {s}"),
             LauncherError::LauncherConfigError => write!(f, "Launcher config error"),
             LauncherError::LoginInvalid(s) => write!(f, "Login data invalid. Failed to find {s}."),
             LauncherError::MutexError(s) => write!(f, "Mutex Lock Error. {s}"),
@@ -201,6 +261,7 @@ impl std::fmt::Display for LauncherError {
             LauncherError::ReqwestError(err) => write!(f, "{err}"),
             LauncherError::SemaphoreError(s) => write!(f, "Semaphore Error. {s}"),
             LauncherError::SendError => write!(f, "Send error"),
+            LauncherError::StatsConfigError => write!(f, "Stats config error"),
             LauncherError::TaskSetNotFound => write!(f, "Task set not found."),
             LauncherError::WeakPtrError => write!(f, "Failed to upgrade a weak pointer"),
             LauncherError::Unknown => write!(f, "Unknown error"),
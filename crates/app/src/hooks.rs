@@ -0,0 +1,167 @@
+//! 启动前和退出后执行的自定义命令
+
+use log::{error, info};
+use tokio::process::Command;
+
+use crate::LauncherError;
+
+/// 命令中可用的模板变量
+/// exit_code: 仅退出后的命令可用
+pub fn hook_vars(
+    game_dir: &str,
+    version_name: &str,
+    java_path: &str,
+    exit_code: Option<i32>,
+) -> Vec<(&'static str, String)> {
+    // 命令在游戏目录中执行，需要使用绝对路径
+    let game_dir = std::fs::canonicalize(game_dir)
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or(game_dir.to_string());
+    let mut vars = vec![
+        ("game_directory", game_dir),
+        ("version_name", version_name.to_string()),
+        ("java_path", java_path.to_string()),
+    ];
+    if let Some(code) = exit_code {
+        vars.push(("exit_code", code.to_string()));
+    }
+    vars
+}
+
+/// 替换命令中的${xxx}
+/// 值按所在位置转义，${xxx}可以直接使用，也可以放在单引号或双引号中
+#[cfg(unix)]
+fn fill_vars(command: &str, vars: &[(&str, String)]) -> String {
+    let mut result = String::new();
+    let (mut single, mut double) = (false, false);
+    let mut rest = command;
+    while let Some(c) = rest.chars().next() {
+        let var = vars
+            .iter()
+            .find(|(key, _)| rest.starts_with(&format!("${{{key}}}")));
+        if let Some((key, value)) = var {
+            if single {
+                result.push_str(&value.replace('\'', "'\\''"));
+            } else if double {
+                for c in value.chars() {
+                    if "\"\\$`".contains(c) {
+                        result.push('\\');
+                    }
+                    result.push(c);
+                }
+            } else {
+                result.push_str(&utils::shell::quote(value));
+            }
+            rest = &rest[key.len() + 3..];
+            continue;
+        }
+        match c {
+            '\'' if !double => single = !single,
+            '"' if !single => double = !double,
+            // 转义的字符原样保留
+            '\\' if !single => {
+                if let Some(next) = rest[1..].chars().next() {
+                    result.push(c);
+                    result.push(next);
+                    rest = &rest[1 + next.len_utf8()..];
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        result.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    result
+}
+
+/// 替换命令中的${xxx}
+#[cfg(not(unix))]
+fn fill_vars(command: &str, vars: &[(&str, String)]) -> String {
+    let mut command = command.to_string();
+    for (key, value) in vars {
+        command = command.replace(&format!("${{{key}}}"), value);
+    }
+    command
+}
+
+fn shell(command: &str) -> Command {
+    #[cfg(windows)]
+    {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    }
+    #[cfg(not(windows))]
+    {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    }
+}
+
+/// 依次执行命令，某条命令失败时停止执行并返回LauncherError::HookFailed
+pub async fn run_hooks(
+    commands: &[String],
+    vars: &[(&str, String)],
+    work_dir: &str,
+) -> Result<(), LauncherError> {
+    for command in commands.iter().filter(|c| !c.trim().is_empty()) {
+        let command = fill_vars(command, vars);
+        info!("Run hook: {command}");
+        let status = shell(&command)
+            .current_dir(work_dir)
+            .status()
+            .await
+            .map_err(|e| {
+                error!("{e}");
+                LauncherError::HookFailed(command.clone())
+            })?;
+        if !status.success() {
+            error!("Hook exited with {status}: {command}");
+            return Err(LauncherError::HookFailed(command));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use utils::shell::split;
+
+    fn vars() -> Vec<(&'static str, String)> {
+        vec![("game_directory", "/home/a b/it's \"$x\"".to_string())]
+    }
+
+    fn args(command: &str) -> Vec<String> {
+        split(&fill_vars(command, &vars())).unwrap_or_default()
+    }
+
+    #[test]
+    fn unquoted_placeholder() {
+        assert_eq!(
+            args("cd ${game_directory}"),
+            ["cd", "/home/a b/it's \"$x\""]
+        );
+    }
+
+    #[test]
+    fn quoted_placeholder() {
+        let expected = ["cd", "/home/a b/it's \"$x\""];
+        assert_eq!(args("cd \"${game_directory}\""), expected);
+        assert_eq!(args("cd '${game_directory}'"), expected);
+        assert_eq!(
+            args("echo \"dir: ${game_directory}/logs\""),
+            ["echo", "dir: /home/a b/it's \"$x\"/logs"]
+        );
+    }
+
+    #[test]
+    fn escaped_quote() {
+        assert_eq!(
+            args("echo \\\" ${game_directory}"),
+            ["echo", "\"", "/home/a b/it's \"$x\""]
+        );
+    }
+}
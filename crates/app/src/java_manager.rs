@@ -0,0 +1,177 @@
+use log::warn;
+use serde_json::json;
+use std::fs::{self, canonicalize, exists};
+use std::path::Path;
+
+use crate::errors::LauncherError;
+use java::java::JavaInstallation;
+
+/// 受管理的Java
+pub struct ManagedJava {
+    /// 由厂商和主版本生成，实例通过它引用Java
+    pub id: String,
+    pub java: JavaInstallation,
+}
+
+pub struct JavaManager {
+    java_list: Vec<ManagedJava>,
+}
+
+impl JavaManager {
+    /// 首次运行时没有java.json，自动扫描系统中的Java
+    pub fn new(runtimes_dir: &str) -> Result<Self, LauncherError> {
+        let mut manager = Self {
+            java_list: Vec::new(),
+        };
+        if exists("java.json")? {
+            manager.i_load()?;
+        } else {
+            manager.scan(runtimes_dir)?;
+        }
+        Ok(manager)
+    }
+
+    /// 添加Java，path可以是java home或java可执行文件，返回id
+    /// 已添加过时返回原有的id
+    pub fn add(&mut self, path: &str) -> Result<String, LauncherError> {
+        let mut home = Path::new(path).to_path_buf();
+        if home.is_file() {
+            // bin/java
+            home = home
+                .parent()
+                .and_then(|bin| bin.parent())
+                .ok_or(LauncherError::InvalidJava(path.to_string()))?
+                .to_path_buf();
+        }
+        let home = canonicalize(&home)
+            .map_err(|_| LauncherError::InvalidJava(path.to_string()))?
+            .to_string_lossy()
+            .to_string();
+        if let Some(managed) = self.java_list.iter().find(|m| m.java.get_path() == home) {
+            return Ok(managed.id.clone());
+        }
+
+        let java = JavaInstallation::new(home)
+            .map_err(|_| LauncherError::InvalidJava(path.to_string()))?;
+        java.get_java_path()
+            .map_err(|_| LauncherError::InvalidJava(path.to_string()))?;
+        let id = self.i_new_id(&java);
+        self.java_list.push(ManagedJava {
+            id: id.clone(),
+            java,
+        });
+        self.save()?;
+        Ok(id)
+    }
+
+    pub fn del(&mut self, index: u32) -> Result<(), LauncherError> {
+        if index as usize >= self.java_list.len() {
+            return Err(LauncherError::OutOfRange);
+        }
+        self.java_list.remove(index as usize);
+        self.save()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&JavaInstallation> {
+        self.java_list
+            .iter()
+            .find(|managed| managed.id == id)
+            .map(|managed| &managed.java)
+    }
+
+    pub fn get_java_list(&self) -> &Vec<ManagedJava> {
+        &self.java_list
+    }
+
+    /// 添加系统中新发现的Java
+    pub fn scan(&mut self, runtimes_dir: &str) -> Result<(), LauncherError> {
+        for java in java::discovery::find_installations(runtimes_dir) {
+            if self
+                .java_list
+                .iter()
+                .all(|managed| managed.java.get_path() != java.get_path())
+            {
+                let id = self.i_new_id(&java);
+                self.java_list.push(ManagedJava { id, java });
+            }
+        }
+        self.save()
+    }
+
+    /// 如21-eclipse-adoptium，重复时添加序号
+    fn i_new_id(&self, java: &JavaInstallation) -> String {
+        let feature = java.get_version().get_feature();
+        let name = match java.get_vendor() {
+            Some(vendor) => format!("{feature}-{vendor}"),
+            None => format!("{feature}-java"),
+        }
+        .to_lowercase()
+        .replace(' ', "-");
+
+        let mut id = name.clone();
+        let mut n = 2;
+        while self.java_list.iter().any(|managed| managed.id == id) {
+            id = format!("{name}-{n}");
+            n += 1;
+        }
+        id
+    }
+
+    /// 已不存在的Java会被移除
+    fn i_load(&mut self) -> Result<(), LauncherError> {
+        let json = serde_json::from_str::<serde_json::Value>(&fs::read_to_string("java.json")?)?;
+        let mut removed = false;
+        for item in json["java"]
+            .as_array()
+            .ok_or(LauncherError::JavaConfigError)?
+        {
+            let id = item["id"]
+                .as_str()
+                .ok_or(LauncherError::JavaConfigError)?
+                .to_string();
+            let path = item["path"]
+                .as_str()
+                .ok_or(LauncherError::JavaConfigError)?
+                .to_string();
+            match JavaInstallation::new(path.clone()) {
+                Ok(java) if java.get_java_path().is_ok() => {
+                    self.java_list.push(ManagedJava { id, java })
+                }
+                _ => {
+                    warn!("Java {id} in {path} no longer exists.");
+                    removed = true;
+                }
+            }
+        }
+        if removed {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    pub fn save(&self) -> Result<(), LauncherError> {
+        let java_list: Vec<serde_json::Value> = self
+            .java_list
+            .iter()
+            .map(|managed| {
+                json!({
+                    "id": managed.id,
+                    "path": managed.java.get_path(),
+                })
+            })
+            .collect();
+        fs::write("java.json", json!({ "java": java_list }).to_string())?;
+        Ok(())
+    }
+}
+
+pub fn frontend_java(managed: &ManagedJava) -> frontend::java::JavaInfo {
+    let java = &managed.java;
+    frontend::java::JavaInfo {
+        arch: java.get_arch().unwrap_or_default().to_string(),
+        id: managed.id.clone(),
+        path: java.get_path().to_string(),
+        vendor: java.get_vendor().unwrap_or_default().to_string(),
+        version: java.get_version().to_string(),
+    }
+}
@@ -1,8 +1,13 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 mod account;
+mod command;
 mod errors;
+mod hooks;
+mod java_manager;
+mod process;
 mod runtime;
+mod stats;
 mod version;
 
 use log::error;
@@ -39,8 +44,9 @@ async fn main() {
 
     let mut app_window = AppWindow::new().unwrap();
     let update_sender = app_window.get_update_sender();
+    let cmd_sender = app_window.get_cmd_sender();
     let cmd_receiver = app_window.take_cmd_receiver().unwrap();
-    let mut rt = AppRuntime::new(update_sender, cmd_receiver).unwrap();
+    let mut rt = AppRuntime::new(update_sender, cmd_sender, cmd_receiver).unwrap();
 
     tokio::spawn(async move {
         if let Err(e) = rt.run().await {
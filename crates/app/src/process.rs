@@ -0,0 +1,311 @@
+//! 游戏进程管理
+
+use log::{error, info};
+use mc::log_event::EventReader;
+use std::collections::VecDeque;
+use std::fs::{create_dir_all, exists};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+use tokio::sync::oneshot;
+use utils::time::format_time;
+
+use crate::LauncherError;
+
+/// 每个进程保留的输出行数
+const OUTPUT_LINES: usize = 1000;
+
+/// 游戏日志所在的文件夹，相对于启动器目录
+const LOG_DIR: &str = "logs";
+
+/// 进程状态变化，由输出读取任务发送给AppRuntime
+pub enum ProcessEvent {
+    /// 进程id，退出码（被信号终止时为None）
+    Exited(u32, Option<i32>),
+}
+
+/// 结束进程及其启动的所有进程，封装器启动的游戏是其子进程
+async fn kill_tree(child: &mut tokio::process::Child) {
+    let Some(pid) = child.id() else {
+        return;
+    };
+    #[cfg(windows)]
+    let mut cmd = {
+        let mut cmd = tokio::process::Command::new("taskkill");
+        cmd.args(["/T", "/F", "/PID", &pid.to_string()]);
+        cmd
+    };
+    #[cfg(not(windows))]
+    let mut cmd = {
+        // 进程组id即游戏进程的id
+        let mut cmd = tokio::process::Command::new("kill");
+        cmd.args(["-KILL", "--", &format!("-{pid}")]);
+        cmd
+    };
+    cmd.stdout(Stdio::null()).stderr(Stdio::null());
+    match cmd.status().await {
+        Ok(status) if status.success() => return,
+        Ok(status) => error!("Failed to kill the process tree of {pid}: {status}"),
+        Err(e) => error!("Failed to kill the process tree of {pid}: {e}"),
+    }
+    // 只结束直接启动的进程
+    if let Err(e) = child.start_kill() {
+        error!("{e}");
+    }
+}
+
+/// 启动器启动的游戏进程
+pub struct GameProcess {
+    /// 启动器内部的进程id
+    pub id: u32,
+    /// 系统pid
+    pub pid: Option<u32>,
+    /// 版本名
+    pub version: String,
+    /// 游戏目录
+    pub game_dir: String,
+    /// 账号名
+    pub account: String,
+    /// 启动时间
+    pub start_time: SystemTime,
+    /// 本次运行的日志文件
+    pub log_path: String,
+    /// 是否由用户强制结束
+    pub killed: bool,
+    /// 最近的输出
+    output: Arc<Mutex<VecDeque<String>>>,
+    /// 通知输出读取任务结束进程
+    kill_sender: Option<oneshot::Sender<()>>,
+}
+
+impl GameProcess {
+    /// 获取最近的输出
+    pub fn get_output(&self) -> Vec<String> {
+        match self.output.lock() {
+            Ok(output) => output.iter().cloned().collect(),
+            Err(e) => {
+                error!("{e}");
+                Vec::new()
+            }
+        }
+    }
+}
+
+pub struct ProcessManager {
+    next_id: u32,
+    processes: Vec<GameProcess>,
+    event_sender: UnboundedSender<ProcessEvent>,
+    event_receiver: Option<UnboundedReceiver<ProcessEvent>>,
+}
+
+impl ProcessManager {
+    pub fn new() -> Self {
+        let (event_sender, event_receiver) = unbounded_channel();
+        Self {
+            next_id: 0,
+            processes: Vec::new(),
+            event_sender,
+            event_receiver: Some(event_receiver),
+        }
+    }
+
+    pub fn take_event_receiver(&mut self) -> Option<UnboundedReceiver<ProcessEvent>> {
+        self.event_receiver.take()
+    }
+
+    /// 启动游戏，并在后台读取其输出，返回进程id
+    pub fn spawn(
+        &mut self,
+        version: String,
+        game_dir: String,
+        account: String,
+        cmd: Command,
+    ) -> Result<u32, LauncherError> {
+        let start_time = SystemTime::now();
+        let timestamp = start_time
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        if !exists(LOG_DIR)? {
+            create_dir_all(LOG_DIR)?;
+        }
+        let log_path = format!("{LOG_DIR}/{version}-{timestamp}.log");
+        let log_file = std::fs::File::create(&log_path)?;
+
+        let mut cmd = tokio::process::Command::from(cmd);
+        // 游戏在单独的进程组中运行，结束时连同封装器启动的进程一起结束
+        #[cfg(unix)]
+        cmd.process_group(0);
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let mut child = cmd.spawn()?;
+        let stdout = child.stdout.take().ok_or(LauncherError::Unknown)?;
+        let stderr = child.stderr.take().ok_or(LauncherError::Unknown)?;
+
+        let id = self.next_id;
+        self.next_id += 1;
+        let output = Arc::new(Mutex::new(VecDeque::with_capacity(OUTPUT_LINES)));
+        let (kill_sender, mut kill_receiver) = oneshot::channel();
+        let process = GameProcess {
+            id,
+            pid: child.id(),
+            version,
+            game_dir,
+            account,
+            start_time,
+            log_path,
+            killed: false,
+            output: output.clone(),
+            kill_sender: Some(kill_sender),
+        };
+        info!(
+            "Game {} started, pid: {:?}, log: {}.",
+            process.version, process.pid, process.log_path
+        );
+        self.processes.push(process);
+
+        let sender = self.event_sender.clone();
+        tokio::spawn(async move {
+            let mut log_file = tokio::fs::File::from_std(log_file);
+            let mut stdout = BufReader::new(stdout);
+            let mut stderr = BufReader::new(stderr);
+            let (mut stdout_buf, mut stderr_buf) = (Vec::new(), Vec::new());
+            // 日志配置输出的XML事件转换为文本
+            let (mut stdout_events, mut stderr_events) = (EventReader::new(), EventReader::new());
+            let (mut stdout_done, mut stderr_done, mut kill_done) = (false, false, false);
+            while !(stdout_done && stderr_done) {
+                // read_until可被取消，未读完的内容保留在buf中
+                let text = tokio::select! {
+                    result = &mut kill_receiver, if !kill_done => {
+                        kill_done = true;
+                        // 发送端被丢弃时不结束进程
+                        if result.is_ok() {
+                            kill_tree(&mut child).await;
+                        }
+                        continue;
+                    },
+                    result = stdout.read_until(b'\n', &mut stdout_buf), if !stdout_done => {
+                        match take_line(result, &mut stdout_buf, &mut stdout_done) {
+                            Some(line) => stdout_events.feed(&line),
+                            None => stdout_events.flush(),
+                        }
+                    },
+                    result = stderr.read_until(b'\n', &mut stderr_buf), if !stderr_done => {
+                        match take_line(result, &mut stderr_buf, &mut stderr_done) {
+                            Some(line) => stderr_events.feed(&line),
+                            None => stderr_events.flush(),
+                        }
+                    },
+                };
+                let Some(text) = text else {
+                    continue;
+                };
+
+                if let Err(e) = log_file.write_all((text.clone() + "\n").as_bytes()).await {
+                    error!("{e}");
+                }
+                match output.lock() {
+                    Ok(mut output) => {
+                        for line in text.lines() {
+                            if output.len() >= OUTPUT_LINES {
+                                output.pop_front();
+                            }
+                            output.push_back(line.to_string());
+                        }
+                    }
+                    Err(e) => error!("{e}"),
+                }
+            }
+            if let Err(e) = log_file.flush().await {
+                error!("{e}");
+            }
+
+            let code = match child.wait().await {
+                Ok(status) => status.code(),
+                Err(e) => {
+                    error!("{e}");
+                    None
+                }
+            };
+            if let Err(e) = sender.send(ProcessEvent::Exited(id, code)) {
+                error!("{e}");
+            }
+        });
+
+        Ok(id)
+    }
+
+    /// 移除已退出的进程
+    pub fn remove(&mut self, id: u32) -> Option<GameProcess> {
+        let index = self.processes.iter().position(|p| p.id == id)?;
+        Some(self.processes.remove(index))
+    }
+
+    /// 强制结束进程，index: 进程列表中的位置
+    pub fn kill(&mut self, index: usize) -> Result<(), LauncherError> {
+        let process = self
+            .processes
+            .get_mut(index)
+            .ok_or(LauncherError::OutOfRange)?;
+        if let Some(sender) = process.kill_sender.take() {
+            process.killed = true;
+            sender.send(()).map_err(|_| LauncherError::ChannelClosed)?;
+        }
+        Ok(())
+    }
+
+    /// 该游戏目录是否已有游戏在运行
+    pub fn is_running(&self, game_dir: &str) -> bool {
+        self.processes.iter().any(|p| p.game_dir == game_dir)
+    }
+
+    pub fn get_process_list(&self) -> &Vec<GameProcess> {
+        &self.processes
+    }
+}
+
+/// 处理read_until的结果，取出一行输出，非UTF-8的内容替换为U+FFFD
+/// 读到0字节（EOF）或读取出错时结束读取
+fn take_line(result: std::io::Result<usize>, buf: &mut Vec<u8>, done: &mut bool) -> Option<String> {
+    match result {
+        Ok(0) => {
+            *done = true;
+            None
+        }
+        Ok(_) => {
+            let mut line = String::from_utf8_lossy(buf).into_owned();
+            buf.clear();
+            if line.ends_with('\n') {
+                line.pop();
+                if line.ends_with('\r') {
+                    line.pop();
+                }
+            }
+            Some(line)
+        }
+        Err(e) => {
+            // 管道出错后无法恢复，继续读取只会重复出错
+            error!("Failed to read game output: {e}");
+            *done = true;
+            None
+        }
+    }
+}
+
+pub fn frontend_running_game(value: &GameProcess) -> frontend::home::RunningGame {
+    frontend::home::RunningGame {
+        account: value.account.clone(),
+        pid: value.pid.map(|pid| pid.to_string()).unwrap_or_default(),
+        start_time: format_time(
+            value
+                .start_time
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        ),
+        version: value.version.clone(),
+    }
+}
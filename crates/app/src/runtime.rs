@@ -1,45 +1,79 @@
 //! CEMCL Application Runtime
 
 use clipboard::{ClipboardContext, ClipboardProvider};
-use log::{error, info};
+use java::java::JavaInstallation;
+use log::{error, info, warn};
 use mc::{
-    MCInstallation,
+    DownloadTask, MCInstallation,
     account::{Account, auth::AuthPollAction},
+    jvm::JvmPreset,
     manifest::{
-        Fabric, Forge, MCDL, download_fabric, download_forge, download_mc, list_fabric, list_forge,
+        Fabric, Forge, MCDL, NeoForge, download_fabric, download_forge, download_mc,
+        download_neoforge, list_fabric, list_forge,
     },
 };
 use serde_json::json;
-use utils::get_parent_dir;
-use std::{collections::HashMap, fs::{self, create_dir_all, exists, remove_dir_all}, process::Command};
+use std::time::UNIX_EPOCH;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, create_dir_all, exists, remove_dir_all},
+};
 use tokio::time::{Duration, sleep};
+use utils::get_parent_dir;
 
 use crate::{
     account::{frontend_account, to_account_type},
+    command::{GameCommand, detect_wrappers, write_script},
+    hooks::{hook_vars, run_hooks},
+    java_manager::{JavaManager, frontend_java},
+    process::{ProcessEvent, ProcessManager, frontend_running_game},
+    stats::{Session, StatsManager},
     version::{
         ConfigMC, VersionManager, frontend_fabric, frontend_forge, frontend_mc_config,
-        frontend_mc_dl, frontend_mc_info, frontend_mc_type,
+        frontend_mc_dl, frontend_mc_info, frontend_mc_type, frontend_neoforge, to_string_list,
+        to_string_map, to_wrapper,
     },
 };
-use downloader::{Config as DownloaderConfig, DownloadManager, task::TaskInfo};
+use downloader::{Config as DownloaderConfig, DownloadManager};
 use frontend::{
-    UICommand,
+    AskID, MsgID, UICommand,
     UIUpdate::{self, SetAccountIndex},
     game::{MCInfo, ModType},
 };
 
 use crate::{account::AccountManager, errors::LauncherError};
 
+/// Mojang的Java运行时的安装目录
+const RUNTIMES_DIR: &str = "runtimes";
+
+/// 游戏异常退出时在日志中显示的输出行数
+const EXIT_OUTPUT_LINES: usize = 20;
+
+/// 启动时找不到兼容的Java的处理方式
+#[derive(Clone, Copy)]
+enum JavaCheck {
+    /// 询问是否下载Java
+    Ask,
+    /// 已确认下载，失败时询问是否继续启动
+    Download,
+    /// 不检查，直接启动
+    Skip,
+}
+
 #[derive(Clone)]
 pub struct ConfigGeneral {
     /// 启动后关闭启动器
     pub close_after_launch: bool,
     /// .minecraft路径
     pub game_path: String,
+    /// 使用启动器提供的日志配置（XML格式输出）
+    pub launcher_log_config: bool,
 }
 
 #[derive(Clone)]
 pub struct ConfigDL {
+    /// Adoptium API，用于下载Java
+    pub adoptium_source: String,
     /// assets下载源
     pub assets_source: String,
     /// 下载时的最大并发数量
@@ -52,16 +86,20 @@ pub struct ConfigDL {
     pub game_source: String,
     /// libraries下载源
     pub libraries_source: String,
+    /// NeoForge下载源
+    pub neoforge_source: String,
 }
 
 impl From<ConfigDL> for DownloaderConfig {
     fn from(value: ConfigDL) -> Self {
         let mut map = HashMap::new();
+        map.insert("adoptium_source".to_string(), value.adoptium_source);
         map.insert("assets_source".to_string(), value.assets_source);
         map.insert("fabric_source".to_string(), value.fabric_source);
         map.insert("forge_source".to_string(), value.forge_source);
         map.insert("game_source".to_string(), value.game_source);
         map.insert("libraries_source".to_string(), value.libraries_source);
+        map.insert("neoforge_source".to_string(), value.neoforge_source);
         Self {
             concurrency: value.concurrency,
             mirrors: map,
@@ -73,11 +111,13 @@ impl From<DownloaderConfig> for ConfigDL {
     fn from(value: DownloaderConfig) -> Self {
         Self {
             concurrency: value.concurrency,
+            adoptium_source: value.mirrors["adoptium_source"].clone(),
             assets_source: value.mirrors["assets_source"].clone(),
             fabric_source: value.mirrors["fabric_source"].clone(),
             forge_source: value.mirrors["forge_source"].clone(),
             game_source: value.mirrors["game_source"].clone(),
             libraries_source: value.mirrors["libraries_source"].clone(),
+            neoforge_source: value.mirrors["neoforge_source"].clone(),
         }
     }
 }
@@ -85,12 +125,14 @@ impl From<DownloaderConfig> for ConfigDL {
 impl From<frontend::ConfigDL> for ConfigDL {
     fn from(value: frontend::ConfigDL) -> Self {
         Self {
+            adoptium_source: value.adoptium_source,
             assets_source: value.assets_source,
             concurrency: value.concurrency,
             fabric_source: value.fabric_source,
             forge_source: value.forge_source,
             game_source: value.game_source,
             libraries_source: value.libraries_source,
+            neoforge_source: value.neoforge_source,
         }
     }
 }
@@ -100,6 +142,7 @@ impl From<frontend::ConfigGeneral> for ConfigGeneral {
         Self {
             close_after_launch: value.close_after_launch,
             game_path: value.game_path,
+            launcher_log_config: value.launcher_log_config,
         }
     }
 }
@@ -107,9 +150,14 @@ impl From<frontend::ConfigGeneral> for ConfigGeneral {
 impl From<frontend::ConfigMC> for ConfigMC {
     fn from(value: frontend::ConfigMC) -> Self {
         Self {
+            auto_memory: value.auto_memory,
+            env: value.env,
             height: value.height,
             java_path: value.java_path,
+            jvm_preset: value.jvm_preset,
             path: value.path,
+            post_exit: value.post_exit,
+            pre_launch: value.pre_launch,
             width: value.width,
             wrapper: value.wrapper,
             xms: value.xms,
@@ -121,12 +169,14 @@ impl From<frontend::ConfigMC> for ConfigMC {
 impl From<ConfigDL> for frontend::ConfigDL {
     fn from(value: ConfigDL) -> Self {
         Self {
+            adoptium_source: value.adoptium_source,
             assets_source: value.assets_source,
             concurrency: value.concurrency,
             fabric_source: value.fabric_source,
             forge_source: value.forge_source,
             game_source: value.game_source,
             libraries_source: value.libraries_source,
+            neoforge_source: value.neoforge_source,
         }
     }
 }
@@ -136,6 +186,7 @@ impl From<ConfigGeneral> for frontend::ConfigGeneral {
         Self {
             close_after_launch: value.close_after_launch,
             game_path: value.game_path,
+            launcher_log_config: value.launcher_log_config,
         }
     }
 }
@@ -143,9 +194,14 @@ impl From<ConfigGeneral> for frontend::ConfigGeneral {
 impl From<ConfigMC> for frontend::ConfigMC {
     fn from(value: ConfigMC) -> Self {
         Self {
+            auto_memory: value.auto_memory,
+            env: value.env,
             height: value.height,
             java_path: value.java_path,
+            jvm_preset: value.jvm_preset,
             path: value.path,
+            post_exit: value.post_exit,
+            pre_launch: value.pre_launch,
             width: value.width,
             wrapper: value.wrapper,
             xms: value.xms,
@@ -159,6 +215,7 @@ impl Default for ConfigGeneral {
         ConfigGeneral {
             close_after_launch: false,
             game_path: String::from(".minecraft"),
+            launcher_log_config: false,
         }
     }
 }
@@ -166,12 +223,14 @@ impl Default for ConfigGeneral {
 impl Default for ConfigDL {
     fn default() -> Self {
         ConfigDL {
+            adoptium_source: String::from("https://api.adoptium.net"),
             assets_source: String::from("https://resources.download.minecraft.net"),
             concurrency: 10,
             fabric_source: String::from("https://maven.fabricmc.net"),
             forge_source: String::from("https://files.minecraftforge.net"),
             game_source: String::from("https://piston-meta.mojang.com"),
             libraries_source: String::from("https://libraries.minecraft.net"),
+            neoforge_source: String::from("https://maven.neoforged.net/releases"),
         }
     }
 }
@@ -179,11 +238,16 @@ impl Default for ConfigDL {
 impl Default for ConfigMC {
     fn default() -> Self {
         ConfigMC {
+            auto_memory: true,
+            env: BTreeMap::new(),
             height: 600,
             java_path: String::from("java"),
+            jvm_preset: mc::jvm::DEFAULT_PRESET.to_string(),
             path: ConfigGeneral::default().game_path,
+            post_exit: Vec::new(),
+            pre_launch: Vec::new(),
             width: 800,
-            wrapper: String::new(),
+            wrapper: Vec::new(),
             xms: String::from("1G"),
             xmx: String::from("2G"),
         }
@@ -194,6 +258,7 @@ struct CacheData {
     dl_mc_list: Option<Vec<MCDL>>,
     dl_fabric_list: Option<Vec<Fabric>>,
     dl_forge_list: Option<Vec<Forge>>,
+    dl_neoforge_list: Option<Vec<NeoForge>>,
 }
 
 impl CacheData {
@@ -202,6 +267,7 @@ impl CacheData {
             dl_fabric_list: None,
             dl_forge_list: None,
             dl_mc_list: None,
+            dl_neoforge_list: None,
         }
     }
 }
@@ -210,8 +276,13 @@ pub struct AppRuntime {
     account_manager: AccountManager,
     cache: CacheData,
     config: ConfigGeneral,
+    cmd_sender: tokio::sync::mpsc::UnboundedSender<UICommand>,
     cmd_receiver: Option<tokio::sync::mpsc::UnboundedReceiver<UICommand>>,
     downloader: DownloadManager,
+    java_manager: JavaManager,
+    jvm_presets: Vec<JvmPreset>,
+    process_manager: ProcessManager,
+    stats_manager: StatsManager,
     update_sender: tokio::sync::mpsc::UnboundedSender<UIUpdate>,
     version_manager: VersionManager,
 }
@@ -219,19 +290,27 @@ pub struct AppRuntime {
 impl AppRuntime {
     pub fn new(
         update_sender: tokio::sync::mpsc::UnboundedSender<UIUpdate>,
+        cmd_sender: tokio::sync::mpsc::UnboundedSender<UICommand>,
         cmd_receiver: tokio::sync::mpsc::UnboundedReceiver<UICommand>,
     ) -> Result<Self, LauncherError> {
         let account_manager = AccountManager::new()?;
-        let (config_dl, config_general, config_mc) = AppRuntime::i_load_config()?;
+        let (config_dl, config_general, config_mc, jvm_presets) = AppRuntime::i_load_config()?;
         let downloader = DownloadManager::new(config_dl.into());
+        let java_manager = JavaManager::new(RUNTIMES_DIR)?;
         let version_manager = VersionManager::new(config_mc.clone())?;
+        let stats_manager = StatsManager::new()?;
 
         Ok(Self {
             account_manager,
             cache: CacheData::new(),
             config: config_general,
+            cmd_sender,
             cmd_receiver: Some(cmd_receiver),
             downloader,
+            java_manager,
+            jvm_presets,
+            process_manager: ProcessManager::new(),
+            stats_manager,
             update_sender,
             version_manager,
         })
@@ -241,6 +320,8 @@ impl AppRuntime {
         self.refresh_ui_info()?;
         self.refresh_ui_acc_list()?;
         self.refresh_ui_config()?;
+        self.refresh_ui_java_list()?;
+        self.refresh_ui_jvm_presets()?;
         self.refresh_ui_version_list()?;
         Ok(())
     }
@@ -311,55 +392,49 @@ impl AppRuntime {
 
                             let task_info =
                                 download_forge(&version.version, forge.clone(), "{forge_source}");
-                            
-                            let dir = get_parent_dir(&task_info.save_path);
-                            if !exists(&dir)? {
-                                create_dir_all(&dir)?;
-                            }
-
-                            let java_path = config.java_path.clone();
-                            let forge_path = task_info.save_path.clone();
-                            let f = move || {
-                                match Command::new(&java_path)
-                                    .arg("-jar")
-                                    .arg(&forge_path)
-                                    .spawn()
-                                {
-                                    Ok(mut child) => {
-                                        if let Err(e) = child.wait() {
-                                            error!("{e}");
-                                            if exists(&dir).unwrap() {
-                                                remove_dir_all(&dir).unwrap();
-                                            }
-                                        }
-                                    }
-                                    Err(e) => error!("Failed to run forge installer. Reason: {e}."),
-                                }
+                            ver = self
+                                .i_add_game_forge(task_info.url, task_info.save_path, &config)
+                                .await?;
+                        }
+                        ModType::NeoForge => {
+                            let mod_list = if let Some(list) = self.cache.dl_neoforge_list.take() {
+                                list
+                            } else {
+                                let config_dl: ConfigDL =
+                                    self.downloader.get_config().clone().into();
+                                mc::manifest::list_neoforge(
+                                    &version.version,
+                                    &config_dl.neoforge_source,
+                                )
+                                .await?
                             };
 
-                            let task = TaskInfo::new(
-                                task_info.url,
-                                task_info.save_path,
-                                None,
-                                Some(Box::new(f)),
-                                None,
-                                None,
-                            );
+                            let neoforge = &mod_list[mod_index as usize];
 
-                            let id = format!("{0}-forge-{1}", &version.version, &forge.version);
-                            self.downloader.add_taskset(id.clone(), vec![task]);
-                            self.downloader.start_taskset(id.clone())?;
-                            ver = id;
+                            let task_info = download_neoforge(
+                                &version.version,
+                                neoforge.clone(),
+                                "{neoforge_source}",
+                            );
+                            ver = self
+                                .i_add_game_forge(task_info.url, task_info.save_path, &config)
+                                .await?;
                         }
                     }
                 }
                 let installation = MCInstallation {
+                    auto_memory: config.auto_memory,
                     description: config.description,
+                    env: config.env,
                     game_args: config.game_args,
                     game_type: ver_type,
                     height: config.height,
+                    java_id: config.java_id,
                     java_path: config.java_path,
                     jvm_args: config.jvm_args,
+                    jvm_preset: config.jvm_preset,
+                    post_exit: config.post_exit,
+                    pre_launch: config.pre_launch,
                     separated: config.separated,
                     version: ver,
                     width: config.width,
@@ -371,16 +446,64 @@ impl AppRuntime {
                 self.refresh_ui_version_list()?;
                 self.update_sender.send(UIUpdate::QuitAddGameDialog)?;
             }
+            UICommand::AddJava(path) => match self.java_manager.add(&path) {
+                Ok(_) => {
+                    self.refresh_ui_java_list()?;
+                    self.update_sender.send(UIUpdate::QuitAddJavaDialog)?;
+                }
+                Err(LauncherError::InvalidJava(path)) => {
+                    self.update_sender
+                        .send(UIUpdate::MsgBox(MsgID::InvalidJava(path.clone())))?;
+                    return Err(LauncherError::InvalidJava(path));
+                }
+                Err(e) => return Err(e),
+            },
             UICommand::DelAccount(index) => {
                 self.account_manager.del(index)?;
                 self.refresh_ui_acc_list()?;
             }
             UICommand::DelGame(index) => {
+                let version = self.version_manager.get(index).version.clone();
                 self.version_manager.del(index)?;
+                self.stats_manager.del(&version)?;
                 self.refresh_ui_version_list()?;
                 self.update_sender.send(UIUpdate::QuitEditGameDialog)?;
             }
-            UICommand::DelJava(index) => {}
+            UICommand::DelJava(index) => {
+                let managed = self
+                    .java_manager
+                    .get_java_list()
+                    .get(index as usize)
+                    .ok_or(LauncherError::OutOfRange)?;
+                let (id, java_path) = (managed.id.clone(), managed.java.get_java_path().ok());
+                self.java_manager.del(index)?;
+
+                // 使用该Java的版本改为使用其路径
+                let mut changed = false;
+                for ver_index in 0..self.version_manager.get_version_list().len() as u32 {
+                    let version = self.version_manager.get_mut(ver_index);
+                    if version.java_id != id {
+                        continue;
+                    }
+                    version.java_id.clear();
+                    if let Some(java_path) = &java_path {
+                        version.java_path = java_path.clone();
+                    }
+                    warn!(
+                        "Java {id} is deleted, {} uses {} now.",
+                        version.version, version.java_path
+                    );
+                    changed = true;
+                }
+                if changed {
+                    self.version_manager.save()?;
+                }
+                self.refresh_ui_java_list()?;
+            }
+            UICommand::DownloadJavaAndStart(acc_index, ver_index) => {
+                self.i_start(acc_index, ver_index, JavaCheck::Download)
+                    .await?;
+            }
             UICommand::EditAccount(index, account) => {
                 let mut i_account = self.account_manager.get(index).clone();
                 i_account.account_type = to_account_type(account.account_type);
@@ -392,11 +515,17 @@ impl AppRuntime {
             }
             UICommand::EditGame(index, installation) => {
                 let mut version = self.version_manager.get(index).clone();
+                version.auto_memory = installation.auto_memory;
                 version.description = installation.description;
+                version.env = installation.env;
                 version.game_args = installation.game_args;
                 version.height = installation.height;
+                version.java_id = installation.java_id;
                 version.java_path = installation.java_path;
                 version.jvm_args = installation.jvm_args;
+                version.jvm_preset = installation.jvm_preset;
+                version.post_exit = installation.post_exit;
+                version.pre_launch = installation.pre_launch;
                 version.separated = installation.separated;
                 version.width = installation.width;
                 version.wrapper = installation.wrapper;
@@ -406,6 +535,48 @@ impl AppRuntime {
                 self.refresh_ui_version_list()?;
                 self.update_sender.send(UIUpdate::QuitEditGameDialog)?;
             }
+            UICommand::ExportLaunchScript(ver_index, redact_token) => {
+                let acc_index = self.account_manager.get_current_index();
+                if acc_index >= self.account_manager.get_account_list().len() as u32
+                    || ver_index >= self.version_manager.get_version_list().len() as u32
+                {
+                    return Err(LauncherError::OutOfRange);
+                }
+
+                // 与启动游戏使用相同的版本和Java，导出的脚本需要文件完整
+                let (version, cmd_list) = self
+                    .i_prepare_launch(acc_index, ver_index, JavaCheck::Skip)
+                    .await?;
+                let version = &version;
+                let account = self.account_manager.get(acc_index);
+
+                let token = if redact_token {
+                    Some(account.access_token.as_str())
+                } else {
+                    None
+                };
+                let game_command =
+                    GameCommand::new(version, self.version_manager.get_config(), cmd_list)?;
+                let script = game_command.to_shell_script(token);
+                let path = format!(
+                    "{}/versions/{}/launch.sh",
+                    self.config.game_path, version.version
+                );
+                write_script(&path, &script)?;
+
+                let path = std::path::Path::new(&game_command.work_dir)
+                    .join(&path)
+                    .to_string_lossy()
+                    .to_string();
+                info!("Launch script is exported to {path}.");
+
+                self.update_sender
+                    .send(UIUpdate::SetHomePageStatus(frontend::home::State::Spare))?;
+                self.update_sender
+                    .send(UIUpdate::SetHomePageProgress(0, 0))?;
+                self.update_sender
+                    .send(UIUpdate::ShowLaunchScript(path, script))?;
+            }
             UICommand::FinishLogin => {
                 let mut session = self
                     .account_manager
@@ -428,11 +599,17 @@ impl AppRuntime {
                 let config = self.version_manager.get_config();
                 self.update_sender
                     .send(UIUpdate::SetAddGameDefault(frontend::game::MCConfig {
+                        auto_memory: config.auto_memory,
                         description: String::new(),
+                        env: BTreeMap::new(),
                         game_args: Vec::new(),
                         height: config.height,
+                        java_id: String::new(),
                         java_path: config.java_path.clone(),
                         jvm_args: Vec::new(),
+                        jvm_preset: config.jvm_preset.clone(),
+                        post_exit: Vec::new(),
+                        pre_launch: Vec::new(),
                         separated: false,
                         width: config.width,
                         wrapper: config.wrapper.clone(),
@@ -522,6 +699,43 @@ impl AppRuntime {
                 self.update_sender
                     .send(UIUpdate::SetAddModListForge(forge_list))?;
             }
+            UICommand::GetAddModListNeoForge(mc_type, index) => {
+                let mut list = if let Some(list) = &self.cache.dl_mc_list {
+                    list.clone()
+                } else {
+                    let list = mc::manifest::list_game(self.config.game_path.clone()).await?;
+                    self.cache.dl_mc_list = Some(list.clone());
+                    list
+                };
+
+                if let Some(mc_type) = mc_type {
+                    list = list
+                        .into_iter()
+                        .filter(|v| frontend_mc_type(v.game_type.clone()) == mc_type)
+                        .collect();
+                }
+
+                let mc = list[index as usize].clone();
+
+                let neoforge_list = if let Some(list) = self.cache.dl_neoforge_list.take() {
+                    list
+                } else {
+                    let config_dl: ConfigDL = self.downloader.get_config().clone().into();
+                    let list = mc::manifest::list_neoforge(&mc.version, &config_dl.neoforge_source)
+                        .await?;
+                    self.cache.dl_neoforge_list = Some(list.clone());
+                    list
+                }
+                .into_iter()
+                .map(|v| frontend_neoforge(v))
+                .collect();
+                self.update_sender
+                    .send(UIUpdate::SetAddModListNeoForge(neoforge_list))?;
+            }
+            UICommand::GetDetectedWrappers => {
+                self.update_sender
+                    .send(UIUpdate::SetDetectedWrappers(detect_wrappers()))?;
+            }
             UICommand::GetEditGameConfig(index) => {
                 self.update_sender
                     .send(UIUpdate::SetEditGameConfig(frontend_mc_config(
@@ -539,57 +753,51 @@ impl AppRuntime {
                 self.version_manager.set_config(config.mc.into());
                 self.save_config()?;
             }
+            UICommand::GetAdoptiumReleases => {
+                let config_dl: ConfigDL = self.downloader.get_config().clone().into();
+                let list = java::adoptium::list_releases(&config_dl.adoptium_source).await?;
+                self.update_sender
+                    .send(UIUpdate::SetAdoptiumReleases(list))?;
+            }
+            UICommand::GetJavaList => {
+                self.refresh_ui_java_list()?;
+            }
+            UICommand::GetJvmPresets => {
+                self.refresh_ui_jvm_presets()?;
+            }
             UICommand::GetOfflineAccount => {
                 self.update_sender
                     .send(UIUpdate::SetOfflineAccount(frontend_account(
                         Account::default(),
                     )))?;
             }
-            UICommand::RequestLogin => {
-                let (uri, code) = self.account_manager.request_login().await?;
-                let mut ctx: ClipboardContext = ClipboardProvider::new()?;
-                ctx.set_contents(code)?;
-                webbrowser::open(&uri)?;
+            UICommand::InstallAdoptium(feature) => {
+                let result = self.i_install_adoptium(feature).await;
+                self.update_sender
+                    .send(UIUpdate::SetHomePageStatus(frontend::home::State::Spare))?;
+                self.update_sender
+                    .send(UIUpdate::SetHomePageProgress(0, 0))?;
+                if let Err(e) = result {
+                    self.update_sender
+                        .send(UIUpdate::MsgBox(MsgID::DLFailed(e.to_string())))?;
+                    return Err(e);
+                }
             }
-            UICommand::Start(acc_index, ver_index) => {
-                if acc_index >= self.account_manager.get_account_list().len() as u32
-                    || ver_index >= self.version_manager.get_version_list().len() as u32
-                {
+            UICommand::KillGame(index) => {
+                self.process_manager.kill(index as usize)?;
+            }
+            UICommand::RepairGame(index) => {
+                if index >= self.version_manager.get_version_list().len() as u32 {
                     return Err(LauncherError::OutOfRange);
                 }
 
-                self.update_sender.send(UIUpdate::SetHomePageStatus(
-                    frontend::home::State::LoggingIn,
-                ))?;
-
-                if self
-                    .account_manager
-                    .request_refresh_account(acc_index)
-                    .await?
-                {
+                // 游戏运行时文件被占用
+                let version = self.version_manager.get(index).clone();
+                let game_dir = version.get_game_dir(&self.config.game_path);
+                if self.process_manager.is_running(&game_dir) {
                     self.update_sender
-                        .send(UIUpdate::SetHomePageProgress(1 as u32, 5))?;
-                    let mut session = self
-                        .account_manager
-                        .take_auth_session()
-                        .ok_or(LauncherError::AuthSessionNotFound)?;
-
-                    let mut action = session.poll().await?;
-                    loop {
-                        match action {
-                            AuthPollAction::Continue(s) => {
-                                self.update_sender
-                                    .send(UIUpdate::SetHomePageProgress(s as u32, 5))?;
-                            }
-                            AuthPollAction::Done(account) => {
-                                self.update_sender
-                                    .send(UIUpdate::SetHomePageProgress(5, 5))?;
-                                self.account_manager.edit(acc_index, account)?;
-                                break;
-                            }
-                        }
-                        action = session.poll().await?;
-                    }
+                        .send(UIUpdate::MsgBox(MsgID::GameRunning))?;
+                    return Err(LauncherError::GameRunning);
                 }
 
                 self.update_sender.send(UIUpdate::SetHomePageStatus(
@@ -597,127 +805,389 @@ impl AppRuntime {
                 ))?;
                 tokio::task::yield_now().await;
 
-                let account = self.account_manager.get(acc_index);
-                let version = self.version_manager.get(ver_index);
-                let (cmd_list, dl_list) =
-                    mc::launch::get_launch_command(account, version, &self.config.game_path)
-                        .await?;
-
+                let (report, dl_list) =
+                    mc::repair::repair(&version, &self.config.game_path).await?;
                 if dl_list.len() != 0 {
-                    self.update_sender.send(UIUpdate::SetHomePageStatus(
-                        frontend::home::State::Downloading,
-                    ))?;
-                    tokio::task::yield_now().await;
-                    let tasks: Vec<downloader::task::TaskInfo> = dl_list
-                        .into_iter()
-                        .map(|i| {
-                            downloader::task::TaskInfo::new(
-                                i.url,
-                                i.save_path,
-                                None,
-                                i.on_finish,
-                                None,
-                                None,
-                            )
-                        })
-                        .collect();
-
-                    let id = version.version.clone();
-                    self.downloader.add_taskset(id.clone(), tasks);
-                    self.downloader.start_taskset(id.clone())?;
-
-                    // progress by bytes may update total bytes, which looks strange
-                    // TODO: make it a broadcast in downloader
-                    let mut status = self.downloader.get_status_by_number(id.clone())?;
-                    loop {
-                        match status {
-                            downloader::taskset::TaskSetStatus::Completed(total) => {
-                                self.update_sender.send(UIUpdate::SetHomePageProgress(
-                                    total as u32,
-                                    total as u32,
-                                ))?;
-                                tokio::task::yield_now().await;
-                                break;
-                            }
-                            downloader::taskset::TaskSetStatus::Failed => {
-                                error!("Failed to download {0}.", &id);
-                                return Err(LauncherError::DownloadFailed(id.clone()));
-                            }
-                            downloader::taskset::TaskSetStatus::Cancelled => {
-                                return Err(LauncherError::Interrupted);
-                            }
-                            downloader::taskset::TaskSetStatus::Downloading(downloaded, total) => {
-                                self.update_sender.send(UIUpdate::SetHomePageProgress(
-                                    downloaded as u32,
-                                    total as u32,
-                                ))?;
-                                tokio::task::yield_now().await;
-                            }
-                            downloader::taskset::TaskSetStatus::Paused(downloaded, total) => {
-                                // This case shouldn't happen now. Pause hasn't been implemented
-                                self.update_sender.send(UIUpdate::SetHomePageProgress(
-                                    downloaded as u32,
-                                    total as u32,
-                                ))?;
-                                tokio::task::yield_now().await;
-                            }
-                            downloader::taskset::TaskSetStatus::Pending(total) => {
-                                // TODO: download this game first
-                                self.update_sender
-                                    .send(UIUpdate::SetHomePageProgress(0, total as u32))?;
-                                tokio::task::yield_now().await;
-                            }
-                        }
-                        drop(status);
-                        sleep(Duration::from_millis(500)).await;
-                        status = self.downloader.get_status_by_number(id.clone())?;
-                    }
+                    self.i_download(version.version.clone(), dl_list).await?;
                 }
 
-                self.update_sender.send(UIUpdate::SetHomePageStatus(
-                    frontend::home::State::Launching,
-                ))?;
-                tokio::task::yield_now().await;
                 self.update_sender
-                    .send(UIUpdate::SetHomePageProgress(1, 2))?;
-                tokio::task::yield_now().await;
-
-                let (s, r) = std::sync::mpsc::channel();
-                let mut cmd = Command::new(if version.wrapper.is_empty() {
-                    version.java_path.clone()
-                } else {
-                    version.wrapper.clone()
-                });
-                if !version.wrapper.is_empty() {
-                    cmd.arg(version.java_path.clone());
-                }
-                cmd.args(cmd_list);
+                    .send(UIUpdate::SetHomePageStatus(frontend::home::State::Spare))?;
+                self.update_sender
+                    .send(UIUpdate::SetHomePageProgress(0, 0))?;
+                self.update_sender
+                    .send(UIUpdate::MsgBox(MsgID::RepairFinished(report.to_string())))?;
+            }
+            UICommand::RequestLogin => {
+                let (uri, code) = self.account_manager.request_login().await?;
+                let mut ctx: ClipboardContext = ClipboardProvider::new()?;
+                ctx.set_contents(code)?;
+                webbrowser::open(&uri)?;
+            }
+            UICommand::ScanJava => {
+                self.java_manager.scan(RUNTIMES_DIR)?;
+                self.refresh_ui_java_list()?;
+            }
+            UICommand::SortGamesByRecent => {
+                self.version_manager.sort_by_recent(&self.stats_manager)?;
+                self.refresh_ui_version_list()?;
+            }
+            UICommand::Start(acc_index, ver_index) => {
+                self.i_start(acc_index, ver_index, JavaCheck::Ask).await?;
+            }
+            UICommand::StartAnyway(acc_index, ver_index) => {
+                self.i_start(acc_index, ver_index, JavaCheck::Skip).await?;
+            }
+            UICommand::SwitchAccount(index) => {
+                self.account_manager.set_current_index(index)?;
+            }
+            UICommand::SwitchGame(index) => {
+                self.version_manager.set_current_index(index)?;
+            }
+        }
 
-                std::thread::spawn(move || s.send(cmd.spawn()));
+        Ok(())
+    }
 
-                match r.recv().unwrap() {
-                    Ok(_) => {
+    fn handle_process_event(&mut self, event: ProcessEvent) -> Result<(), LauncherError> {
+        match event {
+            ProcessEvent::Exited(id, code) => {
+                let process = self
+                    .process_manager
+                    .remove(id)
+                    .ok_or(LauncherError::OutOfRange)?;
+                let start = process
+                    .start_time
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0);
+                let end = utils::time::now();
+                let duration = end.saturating_sub(start);
+                // 统计写入失败不影响后续的命令和界面更新
+                if let Err(e) = self.stats_manager.add(
+                    &process.version,
+                    Session {
+                        account: process.account.clone(),
+                        start,
+                        end,
+                        exit_code: code,
+                    },
+                ) {
+                    error!("Failed to save play time of {}: {e}", process.version);
+                }
+                info!(
+                    "{} has played for {} in total.",
+                    process.account,
+                    utils::time::format_duration(
+                        self.stats_manager.get_account_play_time(&process.account)
+                    )
+                );
+                match code {
+                    Some(0) => info!(
+                        "Game {} exited normally after {duration}s.",
+                        process.version
+                    ),
+                    _ if process.killed => {
+                        info!("Game {} was killed after {duration}s.", process.version)
+                    }
+                    _ => {
+                        let output = process.get_output();
+                        let tail = &output[output.len().saturating_sub(EXIT_OUTPUT_LINES)..];
+                        error!(
+                            "Game {} exited with code {code:?} after {duration}s, see {}. Last output:\n{}",
+                            process.version,
+                            process.log_path,
+                            tail.join("\n")
+                        );
+
+                        let report = mc::crash::analyze(
+                            &process.game_dir,
+                            process.start_time,
+                            code,
+                            &output,
+                        );
                         self.update_sender
-                            .send(UIUpdate::SetHomePageProgress(2, 2))?;
-                        tokio::task::yield_now().await;
-                        if self.config.close_after_launch {
-                            self.update_sender.send(UIUpdate::Quit)?;
-                        }
+                            .send(UIUpdate::MsgBox(MsgID::GameCrashed(report.to_string())))?;
                     }
-                    Err(e) => return Err(e.into()),
                 }
 
+                // 退出后的命令在后台执行，不阻塞启动器
+                if let Some(version) = self
+                    .version_manager
+                    .get_version_list()
+                    .iter()
+                    .find(|v| v.version == process.version)
+                {
+                    let post_exit: Vec<String> = self
+                        .version_manager
+                        .get_config()
+                        .post_exit
+                        .iter()
+                        .chain(version.post_exit.iter())
+                        .cloned()
+                        .collect();
+                    let vars = hook_vars(
+                        &process.game_dir,
+                        &version.version,
+                        &version.java_path,
+                        code,
+                    );
+                    let game_dir = process.game_dir.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = run_hooks(&post_exit, &vars, &game_dir).await {
+                            error!("{e}");
+                        }
+                    });
+                }
+
+                self.refresh_ui_running_list()?;
+                self.refresh_ui_version_list()?;
                 self.update_sender
-                    .send(UIUpdate::SetHomePageStatus(frontend::home::State::Spare))?;
+                    .send(UIUpdate::SetGameExited(process.version, code.unwrap_or(-1)))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 启动游戏，check_java: 找不到兼容的Java时的处理方式
+    async fn i_start(
+        &mut self,
+        acc_index: u32,
+        ver_index: u32,
+        check_java: JavaCheck,
+    ) -> Result<(), LauncherError> {
+        if acc_index >= self.account_manager.get_account_list().len() as u32
+            || ver_index >= self.version_manager.get_version_list().len() as u32
+        {
+            return Err(LauncherError::OutOfRange);
+        }
+
+        // 同一游戏目录不能同时运行多个游戏
+        let game_dir = self
+            .version_manager
+            .get(ver_index)
+            .get_game_dir(&self.config.game_path);
+        if self.process_manager.is_running(&game_dir) {
+            self.update_sender
+                .send(UIUpdate::MsgBox(MsgID::GameRunning))?;
+            return Err(LauncherError::GameRunning);
+        }
 
+        let (version, cmd_list) = self
+            .i_prepare_launch(acc_index, ver_index, check_java)
+            .await?;
+        let version = &version;
+        let account = self.account_manager.get(acc_index);
+
+        self.update_sender.send(UIUpdate::SetHomePageStatus(
+            frontend::home::State::Launching,
+        ))?;
+        tokio::task::yield_now().await;
+        self.update_sender
+            .send(UIUpdate::SetHomePageProgress(1, 2))?;
+        tokio::task::yield_now().await;
+
+        // 先执行全局命令，再执行该版本的命令
+        let pre_launch: Vec<String> = self
+            .version_manager
+            .get_config()
+            .pre_launch
+            .iter()
+            .chain(version.pre_launch.iter())
+            .cloned()
+            .collect();
+        let vars = hook_vars(&game_dir, &version.version, &version.java_path, None);
+        if let Err(e) = run_hooks(&pre_launch, &vars, &game_dir).await {
+            if let LauncherError::HookFailed(command) = &e {
                 self.update_sender
-                    .send(UIUpdate::SetHomePageProgress(0, 0))?;
+                    .send(UIUpdate::MsgBox(MsgID::HookFailed(command.clone())))?;
             }
-            UICommand::SwitchAccount(index) => {
-                self.account_manager.set_current_index(index)?;
+            return Err(e);
+        }
+
+        let mut cmd =
+            GameCommand::new(version, self.version_manager.get_config(), cmd_list)?.to_command();
+
+        if self.config.close_after_launch {
+            // 启动器退出后无法读取输出，也无法执行退出后的命令，不再管理该进程
+            cmd.spawn()?;
+            self.update_sender
+                .send(UIUpdate::SetHomePageProgress(2, 2))?;
+            tokio::task::yield_now().await;
+            self.update_sender.send(UIUpdate::Quit)?;
+        } else {
+            self.process_manager.spawn(
+                version.version.clone(),
+                game_dir,
+                account.user_name.clone(),
+                cmd,
+            )?;
+            self.update_sender
+                .send(UIUpdate::SetHomePageProgress(2, 2))?;
+            tokio::task::yield_now().await;
+            self.refresh_ui_running_list()?;
+        }
+
+        self.update_sender
+            .send(UIUpdate::SetHomePageStatus(frontend::home::State::Spare))?;
+
+        self.update_sender
+            .send(UIUpdate::SetHomePageProgress(0, 0))?;
+        Ok(())
+    }
+
+    /// 准备启动：选择Java、刷新账号、生成启动命令并下载缺少的文件
+    /// 启动游戏和导出启动脚本共用该过程
+    async fn i_prepare_launch(
+        &mut self,
+        acc_index: u32,
+        ver_index: u32,
+        check_java: JavaCheck,
+    ) -> Result<(MCInstallation, Vec<String>), LauncherError> {
+        let mut version = self.i_get_launch_version(ver_index)?;
+        self.i_select_java(&mut version, acc_index, ver_index, check_java)
+            .await?;
+        let java = Self::i_read_java(&version);
+        if let Some(java) = &java {
+            self.i_check_java(&version, java)?;
+        }
+
+        self.i_refresh_account(acc_index).await?;
+
+        self.update_sender.send(UIUpdate::SetHomePageStatus(
+            frontend::home::State::Launching,
+        ))?;
+        tokio::task::yield_now().await;
+
+        let account = self.account_manager.get(acc_index);
+        let (mut cmd_list, dl_list) = mc::launch::get_launch_command(
+            account,
+            &version,
+            &self.config.game_path,
+            self.config.launcher_log_config,
+            self.i_get_jvm_flags(&version, java.as_ref()),
+        )
+        .await?;
+
+        if dl_list.len() != 0 {
+            self.i_download(version.version.clone(), dl_list).await?;
+        }
+        mc::launch::check_log_config(&mut cmd_list);
+
+        // 正在运行的同一版本可能仍在使用旧的natives
+        if !self
+            .process_manager
+            .get_process_list()
+            .iter()
+            .any(|process| process.version == version.version)
+        {
+            mc::launch::clean_natives(&cmd_list);
+        }
+        Ok((version, cmd_list))
+    }
+
+    /// 下载启动所需的文件，并在主页显示进度
+    async fn i_download(
+        &self,
+        id: String,
+        dl_list: Vec<DownloadTask>,
+    ) -> Result<(), LauncherError> {
+        self.update_sender.send(UIUpdate::SetHomePageStatus(
+            frontend::home::State::Downloading,
+        ))?;
+        tokio::task::yield_now().await;
+        let tasks: Vec<downloader::task::TaskInfo> = dl_list
+            .into_iter()
+            .map(|i| {
+                downloader::task::TaskInfo::new(i.url, i.save_path, None, i.on_finish, None, None)
+            })
+            .collect();
+
+        self.downloader.add_taskset(id.clone(), tasks);
+        self.downloader.start_taskset(id.clone())?;
+
+        // progress by bytes may update total bytes, which looks strange
+        // TODO: make it a broadcast in downloader
+        let mut status = self.downloader.get_status_by_number(id.clone())?;
+        loop {
+            match status {
+                downloader::taskset::TaskSetStatus::Completed(total) => {
+                    self.update_sender
+                        .send(UIUpdate::SetHomePageProgress(total as u32, total as u32))?;
+                    tokio::task::yield_now().await;
+                    break;
+                }
+                downloader::taskset::TaskSetStatus::Failed => {
+                    error!("Failed to download {0}.", &id);
+                    return Err(LauncherError::DownloadFailed(id.clone()));
+                }
+                downloader::taskset::TaskSetStatus::Cancelled => {
+                    return Err(LauncherError::Interrupted);
+                }
+                downloader::taskset::TaskSetStatus::Downloading(downloaded, total) => {
+                    self.update_sender.send(UIUpdate::SetHomePageProgress(
+                        downloaded as u32,
+                        total as u32,
+                    ))?;
+                    tokio::task::yield_now().await;
+                }
+                downloader::taskset::TaskSetStatus::Paused(downloaded, total) => {
+                    // This case shouldn't happen now. Pause hasn't been implemented
+                    self.update_sender.send(UIUpdate::SetHomePageProgress(
+                        downloaded as u32,
+                        total as u32,
+                    ))?;
+                    tokio::task::yield_now().await;
+                }
+                downloader::taskset::TaskSetStatus::Pending(total) => {
+                    // TODO: download this game first
+                    self.update_sender
+                        .send(UIUpdate::SetHomePageProgress(0, total as u32))?;
+                    tokio::task::yield_now().await;
+                }
             }
-            UICommand::SwitchGame(index) => {
-                self.version_manager.set_current_index(index)?;
+            drop(status);
+            sleep(Duration::from_millis(500)).await;
+            status = self.downloader.get_status_by_number(id.clone())?;
+        }
+
+        Ok(())
+    }
+
+    /// 刷新MSA账号的access_token
+    async fn i_refresh_account(&mut self, acc_index: u32) -> Result<(), LauncherError> {
+        self.update_sender.send(UIUpdate::SetHomePageStatus(
+            frontend::home::State::LoggingIn,
+        ))?;
+
+        if self
+            .account_manager
+            .request_refresh_account(acc_index)
+            .await?
+        {
+            self.update_sender
+                .send(UIUpdate::SetHomePageProgress(1 as u32, 5))?;
+            let mut session = self
+                .account_manager
+                .take_auth_session()
+                .ok_or(LauncherError::AuthSessionNotFound)?;
+
+            let mut action = session.poll().await?;
+            loop {
+                match action {
+                    AuthPollAction::Continue(s) => {
+                        self.update_sender
+                            .send(UIUpdate::SetHomePageProgress(s as u32, 5))?;
+                    }
+                    AuthPollAction::Done(account) => {
+                        self.update_sender
+                            .send(UIUpdate::SetHomePageProgress(5, 5))?;
+                        self.account_manager.edit(acc_index, account)?;
+                        break;
+                    }
+                }
+                action = session.poll().await?;
             }
         }
 
@@ -738,6 +1208,38 @@ impl AppRuntime {
         Ok(())
     }
 
+    fn refresh_ui_running_list(&self) -> Result<(), LauncherError> {
+        self.update_sender.send(UIUpdate::SetRunningGames(
+            self.process_manager
+                .get_process_list()
+                .iter()
+                .map(|p| frontend_running_game(p))
+                .collect(),
+        ))?;
+        Ok(())
+    }
+
+    fn refresh_ui_java_list(&self) -> Result<(), LauncherError> {
+        self.update_sender.send(UIUpdate::SetJavaList(
+            self.java_manager
+                .get_java_list()
+                .iter()
+                .map(|java| frontend_java(java))
+                .collect(),
+        ))?;
+        Ok(())
+    }
+
+    fn refresh_ui_jvm_presets(&self) -> Result<(), LauncherError> {
+        self.update_sender.send(UIUpdate::SetJvmPresets(
+            self.jvm_presets
+                .iter()
+                .map(|preset| preset.name.clone())
+                .collect(),
+        ))?;
+        Ok(())
+    }
+
     fn refresh_ui_config(&self) -> Result<(), LauncherError> {
         let config_general = &self.config;
         let config_dl: ConfigDL = self.downloader.get_config().clone().into();
@@ -771,7 +1273,7 @@ impl AppRuntime {
         self.update_sender.send(UIUpdate::SetGameList(
             version_list
                 .iter()
-                .map(|v| frontend_mc_info(v.clone()))
+                .map(|v| frontend_mc_info(v.clone(), &self.stats_manager))
                 .collect(),
         ))?;
         self.update_sender
@@ -786,6 +1288,10 @@ impl AppRuntime {
             .cmd_receiver
             .take()
             .ok_or(LauncherError::ChannelNotFound)?;
+        let mut process_receiver = self
+            .process_manager
+            .take_event_receiver()
+            .ok_or(LauncherError::ChannelNotFound)?;
         loop {
             tokio::select! {
                 Some(cmd) = cmd_receiver.recv() => {
@@ -795,11 +1301,381 @@ impl AppRuntime {
                         self.update_sender.send(UIUpdate::SetHomePageProgress(0, 0))?;
                     }
                 },
+                Some(event) = process_receiver.recv() => {
+                    if let Err(e) = self.handle_process_event(event) {
+                        error!("{e}");
+                    }
+                },
             }
         }
     }
 
-    fn i_load_config() -> Result<(ConfigDL, ConfigGeneral, ConfigMC), LauncherError> {
+    /// 获取启动用的版本设置，使用受管理的Java，自动设置内存并检查内存设置
+    fn i_get_launch_version(&self, index: u32) -> Result<MCInstallation, LauncherError> {
+        let mut version = self.version_manager.get(index).clone();
+        if !version.java_id.is_empty() {
+            match self
+                .java_manager
+                .get(&version.java_id)
+                .map(|java| java.get_java_path())
+            {
+                Some(Ok(java_path)) => version.java_path = java_path,
+                _ => error!(
+                    "Java {} is not found, {} is used.",
+                    version.java_id, version.java_path
+                ),
+            }
+        }
+        let memory = utils::memory::get_memory_info();
+        if version.auto_memory {
+            match memory {
+                Some(memory) => {
+                    let game_path = &self.config.game_path;
+                    let mods = version.count_mods(game_path);
+                    let has_loader = version.has_mod_loader(game_path);
+                    (version.xms, version.xmx) =
+                        mc::jvm::auto_memory(memory.total, memory.available, mods, has_loader);
+                    info!(
+                        "Memory for {} ({mods} mods, mod loader: {has_loader}): -Xms{} -Xmx{}",
+                        version.version, version.xms, version.xmx
+                    );
+                }
+                None => error!(
+                    "Failed to get the system memory, -Xms{} -Xmx{} are used.",
+                    version.xms, version.xmx
+                ),
+            }
+        }
+
+        if let Err(e) = mc::jvm::check_memory(&version.xms, &version.xmx, memory.map(|m| m.total)) {
+            self.update_sender
+                .send(UIUpdate::MsgBox(MsgID::InvalidMemory(e.to_string())))?;
+            return Err(LauncherError::InvalidMemory(e.to_string()));
+        }
+        Ok(version)
+    }
+
+    /// 设置的Java与版本json要求的主版本不同时，优先选择主版本相同的Java
+    /// 只有更高版本的Java时使用并提示，都没有时询问是否下载，确认后安装Mojang的Java运行时
+    /// 下载失败时询问是否继续启动并取消本次启动
+    async fn i_select_java(
+        &mut self,
+        version: &mut MCInstallation,
+        acc_index: u32,
+        ver_index: u32,
+        check_java: JavaCheck,
+    ) -> Result<(), LauncherError> {
+        let Some(required) = version.get_java_requirement(&self.config.game_path) else {
+            return Ok(());
+        };
+        let current = match java::java::JavaInstallation::get_executable_version(&version.java_path)
+        {
+            Ok(java) if java.get_feature() == required => return Ok(()),
+            Ok(java) => {
+                info!(
+                    "{} is Java {java}, but {} requires Java {required}.",
+                    version.java_path, version.version
+                );
+                Some(java.get_feature())
+            }
+            Err(_) => {
+                error!("Failed to get the version of {}.", version.java_path);
+                None
+            }
+        };
+
+        // 选择主版本最接近的Java
+        let installations = self.java_manager.get_java_list().iter();
+        let compatible = java::java::find_compatible(installations.map(|m| &m.java), required)
+            .and_then(|java| {
+                let java_path = java.get_java_path().ok()?;
+                Some((java.get_version().get_feature(), java_path))
+            });
+        let newer = match compatible {
+            Some((feature, java_path)) if feature == required => {
+                info!("Java {feature} in {java_path} is selected.");
+                version.java_path = java_path;
+                return Ok(());
+            }
+            // 设置的Java已满足要求时不替换
+            _ if current.is_some_and(|feature| feature > required) => current,
+            Some((feature, java_path)) => {
+                version.java_path = java_path;
+                Some(feature)
+            }
+            None => None,
+        };
+        if let Some(feature) = newer {
+            let text = format!(
+                "{} requires Java {required}, but only Java {feature} is found. {} is used.",
+                version.version, version.java_path
+            );
+            warn!("{text}");
+            self.update_sender
+                .send(UIUpdate::MsgBox(MsgID::JavaWarning(text)))?;
+            return Ok(());
+        }
+
+        match check_java {
+            JavaCheck::Ask => {
+                let cmd_sender = self.cmd_sender.clone();
+                self.update_sender.send(UIUpdate::AskBox(
+                    AskID::JavaDLConfirm(required.to_string()),
+                    Box::new(move || {
+                        if let Err(e) =
+                            cmd_sender.send(UICommand::DownloadJavaAndStart(acc_index, ver_index))
+                        {
+                            error!("{e}");
+                        }
+                    }),
+                ))?;
+                return Err(LauncherError::JavaNotFound(required));
+            }
+            JavaCheck::Download => {}
+            JavaCheck::Skip => {
+                error!("No compatible Java for {}, start anyway.", version.version);
+                return Ok(());
+            }
+        }
+
+        if let Some(component) = version.get_java_component(&self.config.game_path) {
+            match self.i_install_runtime(&component).await {
+                Ok(java_path) => {
+                    version.java_path = java_path;
+                    return Ok(());
+                }
+                Err(e) => error!("Failed to install {component}. Reason: {e}"),
+            }
+        }
+
+        // Mojang没有为所有平台提供运行时，如Linux aarch64
+        match self.i_install_adoptium(required).await {
+            Ok(java_path) => {
+                version.java_path = java_path;
+                return Ok(());
+            }
+            Err(e) => error!("Failed to install Java {required} from Adoptium. Reason: {e}"),
+        }
+
+        let cmd_sender = self.cmd_sender.clone();
+        self.update_sender.send(UIUpdate::AskBox(
+            AskID::WrongJavaConfirm(required.to_string()),
+            Box::new(move || {
+                if let Err(e) = cmd_sender.send(UICommand::StartAnyway(acc_index, ver_index)) {
+                    error!("{e}");
+                }
+            }),
+        ))?;
+        Err(LauncherError::JavaNotFound(required))
+    }
+
+    /// 下载Mojang的Java运行时，返回java可执行文件路径
+    async fn i_install_runtime(&mut self, component: &str) -> Result<String, LauncherError> {
+        let files = java::runtime::install_runtime(RUNTIMES_DIR, component).await?;
+        if files.len() != 0 {
+            let dl_list = files
+                .into_iter()
+                .map(|file| DownloadTask::new(file.url, file.save_path, Some(file.on_finish)))
+                .collect();
+            self.i_download(format!("java-{component}"), dl_list)
+                .await?;
+        }
+
+        let home = java::runtime::get_runtime_home(RUNTIMES_DIR, component);
+        let id = self
+            .java_manager
+            .add(&home)
+            .map_err(|_| LauncherError::JavaRuntimeError(component.to_string()))?;
+        self.refresh_ui_java_list()?;
+        self.java_manager
+            .get(&id)
+            .and_then(|java| java.get_java_path().ok())
+            .ok_or(LauncherError::JavaRuntimeError(component.to_string()))
+    }
+
+    /// 从Adoptium下载该主版本的Java并添加到Java列表，返回java可执行文件路径
+    async fn i_install_adoptium(&mut self, feature: u8) -> Result<String, LauncherError> {
+        let config_dl: ConfigDL = self.downloader.get_config().clone().into();
+        let package = java::adoptium::find_package(&config_dl.adoptium_source, feature).await?;
+        info!("Install {} from Adoptium.", package.release_name);
+
+        create_dir_all(RUNTIMES_DIR)?;
+        let save_path = format!("{RUNTIMES_DIR}/{}", package.name);
+        // 下载器会追加到已存在的文件
+        if exists(&save_path)? {
+            fs::remove_file(&save_path)?;
+        }
+        let dl_list = vec![DownloadTask::new(package.link, save_path.clone(), None)];
+        if let Err(e) = self.i_download(package.release_name, dl_list).await {
+            // 不保留下载了一部分的文件
+            if exists(&save_path)? {
+                fs::remove_file(&save_path)?;
+            }
+            return Err(e);
+        }
+
+        let java = java::adoptium::install_package(RUNTIMES_DIR, &save_path, &package.checksum)?;
+        self.java_manager.add(java.get_path())?;
+        self.refresh_ui_java_list()?;
+        java.get_java_path()
+            .map_err(|_| LauncherError::InvalidJava(java.get_path().to_string()))
+    }
+
+    /// 添加游戏时安装Forge或NeoForge，失败时提示，返回版本id
+    /// url, save_path: 安装器的下载地址和储存位置
+    async fn i_add_game_forge(
+        &self,
+        url: String,
+        save_path: String,
+        config: &frontend::game::MCConfig,
+    ) -> Result<String, LauncherError> {
+        // processors使用该实例的Java
+        let java_path = self
+            .java_manager
+            .get(&config.java_id)
+            .and_then(|java| java.get_java_path().ok())
+            .unwrap_or(config.java_path.clone());
+        match self.i_install_forge(url, save_path, java_path).await {
+            Ok(id) => Ok(id),
+            Err(e) => {
+                self.update_sender
+                    .send(UIUpdate::MsgBox(MsgID::InstallFailed(e.to_string())))?;
+                Err(e)
+            }
+        }
+    }
+
+    /// 下载Forge或NeoForge的安装器并直接安装，不运行其界面，返回版本json中的id
+    /// java_path: 用于执行processors的Java
+    async fn i_install_forge(
+        &self,
+        url: String,
+        save_path: String,
+        java_path: String,
+    ) -> Result<String, LauncherError> {
+        let dir = get_parent_dir(&save_path);
+        let result = self.i_run_forge_installer(url, save_path, java_path).await;
+        // 安装器所在的临时文件夹，无论是否成功都删除
+        match exists(&dir) {
+            Ok(true) => {
+                if let Err(e) = remove_dir_all(&dir) {
+                    error!("Failed to remove {dir}: {e}");
+                }
+            }
+            Ok(false) => {}
+            Err(e) => error!("{e}"),
+        }
+        result
+    }
+
+    /// 下载安装器并安装，安装器所在的文件夹由i_install_forge删除
+    async fn i_run_forge_installer(
+        &self,
+        url: String,
+        save_path: String,
+        java_path: String,
+    ) -> Result<String, LauncherError> {
+        let dir = get_parent_dir(&save_path);
+        if !exists(&dir)? {
+            create_dir_all(&dir)?;
+        }
+        // 下载器会追加到已存在的文件
+        if exists(&save_path)? {
+            fs::remove_file(&save_path)?;
+        }
+        let name = save_path.rsplit('/').next().unwrap_or_default().to_string();
+        let dl_list = vec![DownloadTask::new(url, save_path.clone(), None)];
+        self.i_download(name, dl_list).await?;
+
+        let installer = mc::forge::ForgeInstaller::new(&save_path)?;
+        let id = installer.get_id()?.to_string();
+        info!("Install {id}.");
+        let dl_list = installer.prepare(&self.config.game_path)?;
+        if dl_list.len() != 0 {
+            self.i_download(id.clone(), dl_list).await?;
+        }
+
+        self.update_sender.send(UIUpdate::SetHomePageStatus(
+            frontend::home::State::Installing,
+        ))?;
+        tokio::task::yield_now().await;
+        let game_path = self.config.game_path.clone();
+        let update_sender = self.update_sender.clone();
+        tokio::task::spawn_blocking(move || {
+            installer.run_processors(&game_path, &java_path, |done, total| {
+                if let Err(e) =
+                    update_sender.send(UIUpdate::SetHomePageProgress(done as u32, total as u32))
+                {
+                    error!("{e}");
+                }
+            })
+        })
+        .await
+        .map_err(|e| LauncherError::InstallFailed(e.to_string()))??;
+
+        self.update_sender
+            .send(UIUpdate::SetHomePageStatus(frontend::home::State::Spare))?;
+        self.update_sender
+            .send(UIUpdate::SetHomePageProgress(0, 0))?;
+        Ok(id)
+    }
+
+    /// 获取该版本使用的预设中，适用于其Java的JVM参数
+    fn i_get_jvm_flags(
+        &self,
+        version: &MCInstallation,
+        java: Option<&JavaInstallation>,
+    ) -> Vec<String> {
+        let Some(preset) = mc::jvm::find_preset(&self.jvm_presets, &version.jvm_preset) else {
+            error!("Failed to find JVM preset {}.", version.jvm_preset);
+            return Vec::new();
+        };
+        preset.get_flags(java)
+    }
+
+    /// 读取版本使用的Java的版本、厂商等信息
+    fn i_read_java(version: &MCInstallation) -> Option<JavaInstallation> {
+        match JavaInstallation::from_executable(&version.java_path) {
+            Ok(java) => {
+                info!(
+                    "Java version of {}: {}",
+                    version.java_path,
+                    java.get_version()
+                );
+                Some(java)
+            }
+            Err(_) => {
+                error!(
+                    "Failed to read {}, only version-independent JVM flags are used.",
+                    version.java_path
+                );
+                None
+            }
+        }
+    }
+
+    /// 提示可能导致游戏无法运行的问题，不取消启动
+    fn i_check_java(
+        &self,
+        version: &MCInstallation,
+        java: &JavaInstallation,
+    ) -> Result<(), LauncherError> {
+        let warnings = java.check(mc::jvm::parse_memory(&version.xmx));
+        if warnings.len() != 0 {
+            let text = warnings
+                .iter()
+                .map(|warning| warning.to_string())
+                .collect::<Vec<String>>()
+                .join("\n");
+            warn!("{text}");
+            self.update_sender
+                .send(UIUpdate::MsgBox(MsgID::JavaWarning(text)))?;
+        }
+        Ok(())
+    }
+
+    fn i_load_config() -> Result<(ConfigDL, ConfigGeneral, ConfigMC, Vec<JvmPreset>), LauncherError>
+    {
         if fs::exists(&"config.json")? {
             let mut config_dl = ConfigDL::default();
             let mut config_general = ConfigGeneral::default();
@@ -807,6 +1683,11 @@ impl AppRuntime {
             let json: serde_json::Value =
                 serde_json::from_str(&fs::read_to_string("config.json")?.as_str())?;
 
+            // 旧版本的配置文件中没有此项
+            config_dl.adoptium_source = json["adoptium_source"]
+                .as_str()
+                .unwrap_or(&config_dl.adoptium_source)
+                .to_string();
             config_dl.assets_source = String::from(
                 json["assets_source"]
                     .as_str()
@@ -839,6 +1720,13 @@ impl AppRuntime {
                     .as_str()
                     .ok_or(LauncherError::LauncherConfigError)?,
             );
+            // 旧版本的配置文件中没有此项
+            config_general.launcher_log_config =
+                json["launcher_log_config"].as_bool().unwrap_or(false);
+            // 旧版本的配置文件中没有此项
+            config_mc.auto_memory = json["auto_memory"].as_bool().unwrap_or(false);
+            // 旧版本的配置文件中没有此项
+            config_mc.env = to_string_map(&json["env"]).unwrap_or_default();
             config_mc.height = json["height"]
                 .as_u64()
                 .ok_or(LauncherError::LauncherConfigError)? as u32;
@@ -847,19 +1735,38 @@ impl AppRuntime {
                     .as_str()
                     .ok_or(LauncherError::LauncherConfigError)?,
             );
+            // 旧版本的配置文件中没有此项
+            config_mc.jvm_preset = json["jvm_preset"]
+                .as_str()
+                .unwrap_or(mc::jvm::DEFAULT_PRESET)
+                .to_string();
+            // 旧版本的配置文件中没有此项
+            let jvm_presets = match json["jvm_presets"].as_array() {
+                Some(list) => list
+                    .iter()
+                    .map(JvmPreset::from_json)
+                    .collect::<Option<Vec<JvmPreset>>>()
+                    .ok_or(LauncherError::LauncherConfigError)?,
+                None => mc::jvm::builtin_presets(),
+            };
             config_dl.libraries_source = String::from(
                 json["libraries_source"]
                     .as_str()
                     .ok_or(LauncherError::LauncherConfigError)?,
             );
+            // 旧版本的配置文件中没有此项
+            config_dl.neoforge_source = json["neoforge_source"]
+                .as_str()
+                .unwrap_or(&config_dl.neoforge_source)
+                .to_string();
+            // 旧版本的配置文件中没有此项
+            config_mc.post_exit = to_string_list(&json["post_exit"]).unwrap_or_default();
+            config_mc.pre_launch = to_string_list(&json["pre_launch"]).unwrap_or_default();
             config_mc.width = json["width"]
                 .as_u64()
                 .ok_or(LauncherError::LauncherConfigError)? as u32;
-            config_mc.wrapper = String::from(
-                json["wrapper"]
-                    .as_str()
-                    .ok_or(LauncherError::LauncherConfigError)?,
-            );
+            config_mc.wrapper =
+                to_wrapper(&json["wrapper"]).ok_or(LauncherError::LauncherConfigError)?;
             config_mc.xms = String::from(
                 json["xms"]
                     .as_str()
@@ -871,17 +1778,19 @@ impl AppRuntime {
                     .ok_or(LauncherError::LauncherConfigError)?,
             );
 
-            Ok((config_dl, config_general, config_mc))
+            Ok((config_dl, config_general, config_mc, jvm_presets))
         } else {
             Self::i_save_config(
                 ConfigGeneral::default(),
                 ConfigDL::default(),
                 ConfigMC::default(),
+                &mc::jvm::builtin_presets(),
             )?;
             Ok((
                 ConfigDL::default(),
                 ConfigGeneral::default(),
                 ConfigMC::default(),
+                mc::jvm::builtin_presets(),
             ))
         }
     }
@@ -890,19 +1799,32 @@ impl AppRuntime {
         config: ConfigGeneral,
         config_dl: ConfigDL,
         config_mc: ConfigMC,
+        jvm_presets: &[JvmPreset],
     ) -> Result<(), LauncherError> {
         let json = json!(
             {
+                "adoptium_source": config_dl.adoptium_source,
                 "assets_source": config_dl.assets_source,
+                "auto_memory": config_mc.auto_memory,
                 "close_after_launch": config.close_after_launch,
                 "concurrency": config_dl.concurrency,
+                "env": config_mc.env,
                 "fabric_source": config_dl.fabric_source,
                 "forge_source": config_dl.forge_source,
                 "game_path": config.game_path,
                 "game_source": config_dl.game_source,
                 "height": config_mc.height,
                 "java_path": config_mc.java_path,
+                "jvm_preset": config_mc.jvm_preset,
+                "jvm_presets": jvm_presets
+                    .iter()
+                    .map(|preset| preset.to_json())
+                    .collect::<Vec<serde_json::Value>>(),
+                "launcher_log_config": config.launcher_log_config,
                 "libraries_source": config_dl.libraries_source,
+                "neoforge_source": config_dl.neoforge_source,
+                "post_exit": config_mc.post_exit,
+                "pre_launch": config_mc.pre_launch,
                 "width": config_mc.width,
                 "wrapper": config_mc.wrapper,
                 "xms": config_mc.xms,
@@ -918,6 +1840,7 @@ impl AppRuntime {
             self.config.clone(),
             self.downloader.get_config().clone().into(),
             self.version_manager.get_config().clone(),
+            &self.jvm_presets,
         )
     }
 }
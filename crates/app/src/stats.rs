@@ -0,0 +1,140 @@
+//! 游戏时长统计
+
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::fs::{exists, read_to_string, write};
+
+use crate::LauncherError;
+
+/// 一次游戏记录
+#[derive(Clone)]
+pub struct Session {
+    /// 账号名
+    pub account: String,
+    /// 开始时间（unix时间戳）
+    pub start: u64,
+    /// 结束时间（unix时间戳）
+    pub end: u64,
+    /// 退出码，被信号终止时为None
+    pub exit_code: Option<i32>,
+}
+
+impl Session {
+    /// 时长（秒）
+    pub fn duration(&self) -> u64 {
+        self.end.saturating_sub(self.start)
+    }
+}
+
+/// 按版本记录的游戏记录，保存在stats.json
+pub struct StatsManager {
+    sessions: HashMap<String, Vec<Session>>,
+}
+
+impl StatsManager {
+    pub fn new() -> Result<Self, LauncherError> {
+        Ok(Self {
+            sessions: StatsManager::i_load()?,
+        })
+    }
+
+    pub fn add(&mut self, version: &str, session: Session) -> Result<(), LauncherError> {
+        self.sessions
+            .entry(version.to_string())
+            .or_default()
+            .push(session);
+        self.save()
+    }
+
+    /// 删除版本时一并删除其记录
+    pub fn del(&mut self, version: &str) -> Result<(), LauncherError> {
+        if self.sessions.remove(version).is_some() {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// 最后一次游戏的结束时间
+    pub fn get_last_played(&self, version: &str) -> Option<u64> {
+        self.sessions
+            .get(version)?
+            .iter()
+            .map(|session| session.end)
+            .max()
+    }
+
+    /// 该版本的总游戏时长（秒）
+    pub fn get_play_time(&self, version: &str) -> u64 {
+        self.sessions
+            .get(version)
+            .map(|list| list.iter().map(|session| session.duration()).sum())
+            .unwrap_or(0)
+    }
+
+    /// 该账号在所有版本中的总游戏时长（秒）
+    pub fn get_account_play_time(&self, account: &str) -> u64 {
+        self.sessions
+            .values()
+            .flatten()
+            .filter(|session| session.account == account)
+            .map(|session| session.duration())
+            .sum()
+    }
+
+    fn i_load() -> Result<HashMap<String, Vec<Session>>, LauncherError> {
+        let mut sessions = HashMap::new();
+        if !exists("stats.json")? {
+            return Ok(sessions);
+        }
+
+        let json = serde_json::from_str::<Value>(&read_to_string("stats.json")?)?;
+        for (version, list) in json["versions"]
+            .as_object()
+            .ok_or(LauncherError::StatsConfigError)?
+        {
+            let list = list
+                .as_array()
+                .ok_or(LauncherError::StatsConfigError)?
+                .iter()
+                .map(|node| {
+                    Ok(Session {
+                        account: node["account"]
+                            .as_str()
+                            .ok_or(LauncherError::StatsConfigError)?
+                            .to_string(),
+                        start: node["start"]
+                            .as_u64()
+                            .ok_or(LauncherError::StatsConfigError)?,
+                        end: node["end"]
+                            .as_u64()
+                            .ok_or(LauncherError::StatsConfigError)?,
+                        exit_code: node["exit_code"].as_i64().map(|code| code as i32),
+                    })
+                })
+                .collect::<Result<Vec<Session>, LauncherError>>()?;
+            sessions.insert(version.clone(), list);
+        }
+        Ok(sessions)
+    }
+
+    pub fn save(&self) -> Result<(), LauncherError> {
+        let mut json = json!({ "versions": {} });
+        for (version, list) in &self.sessions {
+            json["versions"][version] = list
+                .iter()
+                .map(|session| {
+                    json!({
+                        "account": session.account,
+                        "start": session.start,
+                        "end": session.end,
+                        "duration": session.duration(),
+                        "exit_code": session.exit_code,
+                    })
+                })
+                .collect();
+        }
+
+        write("stats.json", json.to_string())?;
+        Ok(())
+    }
+}
@@ -4,27 +4,40 @@ use frontend::game::MCInfo;
 use log::{error, warn};
 use serde_json::json;
 use std::{
-    collections::HashSet,
+    collections::{BTreeMap, HashSet},
     fs::{create_dir_all, exists, read_to_string, remove_dir_all, write},
 };
 
 use mc::{MCInstallation, manifest::MCDL};
-use utils::list_dir;
+use utils::{
+    list_dir,
+    time::{format_duration, format_time},
+};
 
-use crate::LauncherError;
+use crate::{LauncherError, stats::StatsManager};
 
 #[derive(Clone)]
 pub struct ConfigMC {
+    /// 新版本默认自动设置内存
+    pub auto_memory: bool,
+    /// 所有版本使用的环境变量，版本中的同名变量优先
+    pub env: BTreeMap<String, String>,
     /// 默认游戏窗口高度
     pub height: u32,
     /// java可执行文件路径
     pub java_path: String,
+    /// 默认JVM参数预设名
+    pub jvm_preset: String,
     /// .minecraft位置
     pub path: String,
+    /// 所有版本退出后执行的命令
+    pub post_exit: Vec<String>,
+    /// 所有版本启动前执行的命令
+    pub pre_launch: Vec<String>,
     /// 默认游戏窗口宽度
     pub width: u32,
-    /// 默认封装器
-    pub wrapper: String,
+    /// 默认封装器链
+    pub wrapper: Vec<Vec<String>>,
     /// 默认JVM最小内存
     pub xms: String,
     /// 默认JVM最大内存
@@ -110,6 +123,7 @@ impl VersionManager {
         let mut version_name_set = HashSet::new();
         let mut version_list = Vec::new();
         let mut index = 0;
+        let mut current_version = None;
         let dir = config.path + "/versions";
 
         if !exists(&dir)? {
@@ -125,6 +139,8 @@ impl VersionManager {
             index = json["current"]
                 .as_i64()
                 .ok_or(LauncherError::GameConfigError)? as u32;
+            // 旧版本的配置文件中没有此项
+            current_version = json["current_version"].as_str().map(|s| s.to_string());
 
             for (k, v) in json["versions"]
                 .as_object()
@@ -136,10 +152,14 @@ impl VersionManager {
                 }
                 let node = v.as_object().ok_or(LauncherError::GameConfigError)?;
                 let value = MCInstallation {
+                    // 旧版本的配置文件中没有此项
+                    auto_memory: node["auto_memory"].as_bool().unwrap_or(false),
                     description: node["description"]
                         .as_str()
                         .ok_or(LauncherError::GameConfigError)?
                         .to_string(),
+                    // 旧版本的配置文件中没有此项
+                    env: to_string_map(&node["env"]).unwrap_or_default(),
                     game_args: node["game_args"]
                         .as_array()
                         .ok_or(LauncherError::GameConfigError)?
@@ -158,6 +178,8 @@ impl VersionManager {
                     height: node["height"]
                         .as_i64()
                         .ok_or(LauncherError::GameConfigError)? as u32,
+                    // 旧版本的配置文件中没有此项
+                    java_id: node["java_id"].as_str().unwrap_or_default().to_string(),
                     java_path: node["java_path"]
                         .as_str()
                         .ok_or(LauncherError::GameConfigError)?
@@ -172,6 +194,13 @@ impl VersionManager {
                                 .map(|s| s.to_string())
                         })
                         .collect::<Result<Vec<String>, LauncherError>>()?,
+                    // 旧版本的配置文件中没有此项
+                    jvm_preset: node["jvm_preset"]
+                        .as_str()
+                        .unwrap_or(mc::jvm::DEFAULT_PRESET)
+                        .to_string(),
+                    post_exit: to_string_list(&node["post_exit"]).unwrap_or_default(),
+                    pre_launch: to_string_list(&node["pre_launch"]).unwrap_or_default(),
                     separated: node["separated"]
                         .as_bool()
                         .ok_or(LauncherError::GameConfigError)?,
@@ -179,10 +208,7 @@ impl VersionManager {
                     width: node["width"]
                         .as_i64()
                         .ok_or(LauncherError::GameConfigError)? as u32,
-                    wrapper: node["wrapper"]
-                        .as_str()
-                        .ok_or(LauncherError::GameConfigError)?
-                        .to_string(),
+                    wrapper: to_wrapper(&node["wrapper"]).ok_or(LauncherError::GameConfigError)?,
                     xms: node["xms"]
                         .as_str()
                         .ok_or(LauncherError::GameConfigError)?
@@ -211,11 +237,17 @@ impl VersionManager {
 
             let json = serde_json::from_str::<serde_json::Value>(&read_to_string(&path)?.as_str())?;
             let value = MCInstallation {
+                auto_memory: config.auto_memory,
                 description: String::new(),
+                env: BTreeMap::new(),
                 game_args: Vec::new(),
                 height: config.height,
+                java_id: String::new(),
                 java_path: config.java_path.clone(),
                 jvm_args: Vec::new(),
+                jvm_preset: config.jvm_preset.clone(),
+                post_exit: Vec::new(),
+                pre_launch: Vec::new(),
                 separated: false,
                 game_type: to_mc_type(
                     json["type"]
@@ -224,7 +256,7 @@ impl VersionManager {
                 )?,
                 version: version,
                 width: config.width,
-                wrapper: String::new(),
+                wrapper: Vec::new(),
                 xms: config.xms.clone(),
                 xmx: config.xmx.clone(),
             };
@@ -232,6 +264,13 @@ impl VersionManager {
             version_list.push(value);
         }
 
+        // 列表顺序可能与保存时不同，优先按名称恢复当前版本
+        if let Some(current) = current_version {
+            if let Some(i) = version_list.iter().position(|v| v.version == current) {
+                index = i as u32;
+            }
+        }
+
         Ok((version_list, index))
     }
 
@@ -247,6 +286,10 @@ impl VersionManager {
         let mut json = json!(
             {
                 "current": self.current_index,
+                "current_version": self
+                    .version_list
+                    .get(self.current_index as usize)
+                    .map(|v| v.version.clone()),
                 "versions": {}
             }
         );
@@ -287,6 +330,29 @@ impl VersionManager {
         Ok(())
     }
 
+    /// 按最近游戏时间排序，从未游戏的版本按名称排在最后
+    pub fn sort_by_recent(&mut self, stats: &StatsManager) -> Result<(), LauncherError> {
+        let current = self
+            .version_list
+            .get(self.current_index as usize)
+            .map(|v| v.version.clone());
+        self.version_list.sort_by(|a, b| {
+            stats
+                .get_last_played(&b.version)
+                .cmp(&stats.get_last_played(&a.version))
+                .then_with(|| a.version.cmp(&b.version))
+        });
+        if let Some(current) = current {
+            self.current_index = self
+                .version_list
+                .iter()
+                .position(|v| v.version == current)
+                .unwrap_or(0) as u32;
+        }
+
+        self.save()
+    }
+
     pub fn set_current_index(&mut self, index: u32) -> Result<(), LauncherError> {
         self.current_index = index;
         self.save()
@@ -295,12 +361,18 @@ impl VersionManager {
 
 fn to_json_value(version: &MCInstallation) -> serde_json::Value {
     json!({
+        "auto_memory": version.auto_memory,
         "description": version.description,
+        "env": version.env,
         "game_args": version.game_args,
         "game_type": version.game_type.as_str(),
         "height": version.height,
+        "java_id": version.java_id,
         "java_path": version.java_path,
         "jvm_args": version.jvm_args,
+        "jvm_preset": version.jvm_preset,
+        "post_exit": version.post_exit,
+        "pre_launch": version.pre_launch,
         "separated": version.separated,
         "version": version.version,
         "width": version.width,
@@ -310,6 +382,33 @@ fn to_json_value(version: &MCInstallation) -> serde_json::Value {
     })
 }
 
+/// 读取字符串数组，格式错误时返回None
+pub fn to_string_list(node: &serde_json::Value) -> Option<Vec<String>> {
+    node.as_array()?
+        .iter()
+        .map(|item| item.as_str().map(|s| s.to_string()))
+        .collect()
+}
+
+/// 读取封装器链，旧版本的配置文件中为单个字符串
+pub fn to_wrapper(node: &serde_json::Value) -> Option<Vec<Vec<String>>> {
+    if let Some(s) = node.as_str() {
+        if s.is_empty() {
+            return Some(Vec::new());
+        }
+        return Some(vec![vec![s.to_string()]]);
+    }
+    node.as_array()?.iter().map(to_string_list).collect()
+}
+
+/// 读取字符串键值对，格式错误时返回None
+pub fn to_string_map(node: &serde_json::Value) -> Option<BTreeMap<String, String>> {
+    node.as_object()?
+        .iter()
+        .map(|(k, v)| v.as_str().map(|v| (k.clone(), v.to_string())))
+        .collect()
+}
+
 fn to_mc_type(s: &str) -> Result<mc::MCType, LauncherError> {
     match s {
         "release" => Ok(mc::MCType::Release),
@@ -329,21 +428,32 @@ pub fn frontend_mc_type(mc_type: mc::MCType) -> frontend::game::MCType {
     }
 }
 
-pub fn frontend_mc_info(version: MCInstallation) -> MCInfo {
+pub fn frontend_mc_info(version: MCInstallation, stats: &StatsManager) -> MCInfo {
     MCInfo {
         description: version.description,
         game_type: frontend_mc_type(version.game_type),
+        last_played: stats
+            .get_last_played(&version.version)
+            .map(format_time)
+            .unwrap_or_default(),
+        play_time: format_duration(stats.get_play_time(&version.version)),
         version: version.version,
     }
 }
 
 pub fn frontend_mc_config(config: MCInstallation) -> frontend::game::MCConfig {
     frontend::game::MCConfig {
+        auto_memory: config.auto_memory,
         description: config.description,
+        env: config.env,
         game_args: config.game_args,
         height: config.height,
+        java_id: config.java_id,
         java_path: config.java_path,
         jvm_args: config.jvm_args,
+        jvm_preset: config.jvm_preset,
+        post_exit: config.post_exit,
+        pre_launch: config.pre_launch,
         separated: config.separated,
         width: config.width,
         wrapper: config.wrapper,
@@ -374,3 +484,9 @@ pub fn frontend_forge(forge: mc::manifest::Forge) -> frontend::game::Forge {
         modified: forge.modified,
     }
 }
+
+pub fn frontend_neoforge(neoforge: mc::manifest::NeoForge) -> frontend::game::NeoForge {
+    frontend::game::NeoForge {
+        version: neoforge.version,
+    }
+}
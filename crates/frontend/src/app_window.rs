@@ -3,13 +3,13 @@ use log::error;
 use slint::ComponentHandle;
 use std::sync::{Arc, Mutex};
 
-use crate::game::{MCInfo, ui_combo_box_list, ui_game_dl_list, ui_game_list};
+use crate::game::{MCInfo, ui_combo_box_list, ui_game_dl_list, ui_game_list, ui_jvm_preset_list};
 use crate::settings::Config;
-use crate::ui::{self, AddGameDialog, EditGameDialog, LoginDialog};
+use crate::ui::{self, AddGameDialog, AddJavaDialog, EditGameDialog, LoginDialog};
 use crate::{
     account::{self, Account},
-    game::{self, Fabric, Forge, MCConfig, MCDL, MCType, ModType},
-    home, msg_box,
+    game::{self, Fabric, Forge, MCConfig, MCDL, MCType, ModType, NeoForge},
+    home, java, msg_box,
 };
 
 // UI -> App
@@ -17,22 +17,45 @@ pub enum UICommand {
     /// User name and UUID
     AddOfflineAccount(String, String),
     AddGame(Option<MCType>, u32, Option<ModType>, u32, MCConfig),
+    /// Java home or the java executable
+    AddJava(String),
     DelAccount(u32),
     DelGame(u32),
     DelJava(u32),
+    /// Download the required Java, then start
+    DownloadJavaAndStart(u32, u32),
     EditAccount(u32, Account),
     EditGame(u32, MCConfig),
+    /// Game index and whether to redact the access token
+    ExportLaunchScript(u32, bool),
     FinishLogin,
     GetAddGameDefault,
     GetAddGameList(Option<MCType>),
+    /// Major versions available from Adoptium
+    GetAdoptiumReleases,
     GetAddModListFabric(Option<MCType>, u32),
     GetAddModListForge(Option<MCType>, u32),
+    GetAddModListNeoForge(Option<MCType>, u32),
+    GetDetectedWrappers,
     GetEditGameConfig(u32),
     GetEditGameVersion(u32),
+    GetJavaList,
+    GetJvmPresets,
     GetOfflineAccount,
+    /// Download the Java of the major version from Adoptium
+    InstallAdoptium(u8),
+    /// Index in the running game list
+    KillGame(u32),
+    /// Verify the game files and download the missing ones
+    RepairGame(u32),
     RequestLogin,
+    /// Add the newly found Java installations
+    ScanJava,
     SetConfig(Config),
+    SortGamesByRecent,
     Start(u32, u32),
+    /// Start without checking the Java version
+    StartAnyway(u32, u32),
     SwitchAccount(u32),
     SwitchGame(u32),
 }
@@ -47,18 +70,32 @@ pub enum UIUpdate {
     SetAddGameList(Vec<MCDL>),
     SetAddModListFabric(Vec<Fabric>),
     SetAddModListForge(Vec<Forge>),
+    SetAddModListNeoForge(Vec<NeoForge>),
+    /// Major versions available from Adoptium
+    SetAdoptiumReleases(Vec<u8>),
     SetAuthors(String),
     SetConfig(Config),
+    /// Wrappers found in PATH
+    SetDetectedWrappers(Vec<String>),
     SetEditGameConfig(MCConfig),
     SetEditGameVersion(String),
+    /// Game name and exit code (-1 if terminated by a signal)
+    SetGameExited(String, i32),
     SetHomePageProgress(u32, u32),
     SetHomePageStatus(home::State),
     SetGameIndex(u32),
     SetGameList(Vec<MCInfo>),
+    SetJavaList(Vec<java::JavaInfo>),
+    /// Names of the JVM flag presets
+    SetJvmPresets(Vec<String>),
     SetOfflineAccount(Account),
+    SetRunningGames(Vec<home::RunningGame>),
     SetVersion(String),
+    /// Path and content of the exported launch script
+    ShowLaunchScript(String, String),
     Quit,
     QuitAddGameDialog,
+    QuitAddJavaDialog,
     QuitEditGameDialog,
     QuitLoginDialog,
 }
@@ -106,9 +143,11 @@ where
 pub struct AppWindow {
     ui: crate::ui::AppWindow,
     update_sender: tokio::sync::mpsc::UnboundedSender<UIUpdate>,
+    cmd_sender: tokio::sync::mpsc::UnboundedSender<UICommand>,
     cmd_receiver: Option<tokio::sync::mpsc::UnboundedReceiver<UICommand>>,
     // dialogs
     add_game_dialog: Arc<Mutex<Option<slint::Weak<AddGameDialog>>>>,
+    add_java_dialog: Arc<Mutex<Option<slint::Weak<AddJavaDialog>>>>,
     edit_game_dialog: Arc<Mutex<Option<slint::Weak<EditGameDialog>>>>,
     login_dialog: Arc<Mutex<Option<slint::Weak<LoginDialog>>>>,
 }
@@ -119,6 +158,7 @@ impl AppWindow {
         let (update_tx, mut update_rx) = tokio::sync::mpsc::unbounded_channel();
         let ui = ui::AppWindow::new()?;
         let add_game_dialog = Arc::new(Mutex::new(None));
+        let add_java_dialog = Arc::new(Mutex::new(None));
         let edit_game_dialog = Arc::new(Mutex::new(None));
         let login_dialog = Arc::new(Mutex::new(None));
 
@@ -182,7 +222,23 @@ impl AppWindow {
         });
 
         let tx = cmd_tx.clone();
-        ui.on_open_add_java_dialog(move || {});
+        let dialog = add_java_dialog.clone();
+        ui.on_open_add_java_dialog(move || match dialog.lock() {
+            Ok(mut dialog) => {
+                let tx = tx.clone();
+                match java::add_java_dialog(tx) {
+                    Ok(w) => {
+                        *dialog = Some(w);
+                    }
+                    Err(e) => {
+                        error!("{e}");
+                    }
+                }
+            }
+            Err(e) => {
+                error!("{e}");
+            }
+        });
 
         let tx = cmd_tx.clone();
         let dialog = edit_game_dialog.clone();
@@ -225,13 +281,62 @@ impl AppWindow {
             }
         });
 
+        let tx = cmd_tx.clone();
+        ui.on_get_adoptium_releases(move || {
+            if let Err(e) = tx.send(UICommand::GetAdoptiumReleases) {
+                error!("{e}")
+            }
+        });
+
+        let tx = cmd_tx.clone();
+        ui.on_install_adoptium(move |feature| match feature.parse() {
+            Ok(feature) => {
+                if let Err(e) = tx.send(UICommand::InstallAdoptium(feature)) {
+                    error!("{e}")
+                }
+            }
+            Err(e) => error!("{e}"),
+        });
+
+        let tx = cmd_tx.clone();
+        ui.on_kill_game(move |index| {
+            let tx = tx.clone();
+            if let Err(e) = msg_box::ask_box(msg_box::AskID::KillGameConfirm, move || {
+                if let Err(e) = tx.send(UICommand::KillGame(index as u32)) {
+                    error!("{e}");
+                }
+            }) {
+                error!("{e}")
+            }
+        });
+
+        let tx = cmd_tx.clone();
+        ui.on_scan_java(move || {
+            if let Err(e) = tx.send(UICommand::ScanJava) {
+                error!("{e}")
+            }
+        });
+
+        ui.on_check_config(|wrapper, env| game::check_wrapper_env(&wrapper, &env).into());
+
         let tx = cmd_tx.clone();
         ui.on_set_config(move |config| {
+            // 封装器或环境变量有误时不保存，错误显示在设置页
+            if !game::check_wrapper_env(&config.mc.wrapper, &config.mc.env).is_empty() {
+                return;
+            }
             if let Err(e) = tx.send(UICommand::SetConfig(config.into())) {
                 error!("{e}");
             }
         });
 
+        let tx = cmd_tx.clone();
+        ui.on_sort_games_by_recent(move || {
+            if let Err(e) = tx.send(UICommand::SortGamesByRecent) {
+                error!("{e}")
+            }
+        });
+
         let tx = cmd_tx.clone();
         ui.on_start(move |acc_index, ver_index| {
             if let Err(e) = tx.send(UICommand::Start(acc_index as u32, ver_index as u32)) {
@@ -255,6 +360,7 @@ impl AppWindow {
 
         let ui_weak_clone = ui_weak.clone();
         let add_game_dialog_clone = add_game_dialog.clone();
+        let add_java_dialog_clone = add_java_dialog.clone();
         let edit_game_dialog_clone = edit_game_dialog.clone();
         let login_dialog_clone = login_dialog.clone();
         tokio::spawn(async move {
@@ -263,6 +369,7 @@ impl AppWindow {
                     update,
                     ui_weak_clone.clone(),
                     add_game_dialog_clone.clone(),
+                    add_java_dialog_clone.clone(),
                     edit_game_dialog_clone.clone(),
                     login_dialog_clone.clone(),
                 )
@@ -273,8 +380,10 @@ impl AppWindow {
         Ok(Self {
             ui,
             update_sender: update_tx,
+            cmd_sender: cmd_tx,
             cmd_receiver: Some(cmd_rx),
             add_game_dialog,
+            add_java_dialog,
             edit_game_dialog,
             login_dialog,
         })
@@ -284,17 +393,27 @@ impl AppWindow {
         update: UIUpdate,
         ui_weak: slint::Weak<ui::AppWindow>,
         add_game_dialog: Arc<Mutex<Option<slint::Weak<AddGameDialog>>>>,
+        add_java_dialog: Arc<Mutex<Option<slint::Weak<AddJavaDialog>>>>,
         edit_game_dialog: Arc<Mutex<Option<slint::Weak<EditGameDialog>>>>,
         login_dialog: Arc<Mutex<Option<slint::Weak<LoginDialog>>>>,
     ) {
         match update {
             UIUpdate::AskBox(id, f) => {
-                if let Err(e) = msg_box::ask_box(id, f) {
+                // dialogs must be created in the event loop
+                if let Err(e) = slint::invoke_from_event_loop(move || {
+                    if let Err(e) = msg_box::ask_box(id, f) {
+                        error!("{e}");
+                    }
+                }) {
                     error!("{e}");
                 }
             }
             UIUpdate::MsgBox(id) => {
-                if let Err(e) = msg_box::msg_box(id) {
+                if let Err(e) = slint::invoke_from_event_loop(move || {
+                    if let Err(e) = msg_box::msg_box(id) {
+                        error!("{e}");
+                    }
+                }) {
                     error!("{e}");
                 }
             }
@@ -360,6 +479,18 @@ impl AppWindow {
                     error!("{e}");
                 }
             },
+            UIUpdate::SetAddModListNeoForge(list) => match get(add_game_dialog) {
+                Ok(w) => {
+                    if let Err(e) = w.upgrade_in_event_loop(move |dialog| {
+                        dialog.set_mod_list(game::ui_neoforge_list(&list));
+                    }) {
+                        error!("{e}");
+                    }
+                }
+                Err(e) => {
+                    error!("{e}");
+                }
+            },
             UIUpdate::SetAuthors(authors) => {
                 if let Err(e) = ui_weak.upgrade_in_event_loop(move |ui| {
                     ui.set_authors(authors.into());
@@ -367,6 +498,13 @@ impl AppWindow {
                     error!("{e}")
                 }
             }
+            UIUpdate::SetAdoptiumReleases(list) => {
+                if let Err(e) = ui_weak.upgrade_in_event_loop(move |ui| {
+                    ui.set_adoptium_releases(java::ui_adoptium_releases(&list));
+                }) {
+                    error!("{e}")
+                }
+            }
             UIUpdate::SetConfig(config) => {
                 if let Err(e) = ui_weak.upgrade_in_event_loop(move |ui| {
                     ui.set_config(config.into());
@@ -374,6 +512,25 @@ impl AppWindow {
                     error!("{e}")
                 }
             }
+            UIUpdate::SetDetectedWrappers(list) => {
+                // 两个对话框都可能需要，未打开的对话框忽略
+                let text = list.join(", ");
+                if let Ok(w) = get(add_game_dialog) {
+                    let text = text.clone();
+                    if let Err(e) = w.upgrade_in_event_loop(move |dialog| {
+                        dialog.set_detected_wrappers(text.into());
+                    }) {
+                        error!("{e}");
+                    }
+                }
+                if let Ok(w) = get(edit_game_dialog) {
+                    if let Err(e) = w.upgrade_in_event_loop(move |dialog| {
+                        dialog.set_detected_wrappers(text.into());
+                    }) {
+                        error!("{e}");
+                    }
+                }
+            }
             UIUpdate::SetEditGameConfig(config) => match get(edit_game_dialog) {
                 Ok(w) => {
                     if let Err(e) = w.upgrade_in_event_loop(move |dialog| {
@@ -398,6 +555,14 @@ impl AppWindow {
                     error!("{e}");
                 }
             },
+            UIUpdate::SetGameExited(name, code) => {
+                if let Err(e) = ui_weak.upgrade_in_event_loop(move |ui| {
+                    ui.set_exited_game(name.into());
+                    ui.set_exit_code(code);
+                }) {
+                    error!("{e}")
+                }
+            }
             UIUpdate::SetHomePageProgress(current, total) => {
                 if let Err(e) = ui_weak.upgrade_in_event_loop(move |ui| {
                     ui.set_progress(current as f32 / total as f32);
@@ -427,6 +592,54 @@ impl AppWindow {
                     error!("{e}")
                 }
             }
+            UIUpdate::SetJavaList(list) => {
+                // Java页面和两个对话框都需要，未打开的对话框忽略
+                let list_clone = list.clone();
+                if let Err(e) = ui_weak.upgrade_in_event_loop(move |ui| {
+                    ui.set_java_model(java::ui_java_list(&list_clone));
+                }) {
+                    error!("{e}")
+                }
+                if let Ok(w) = get(add_game_dialog) {
+                    let list = list.clone();
+                    if let Err(e) = w.upgrade_in_event_loop(move |dialog| {
+                        dialog.set_java_ids(java::ui_java_ids(&list));
+                    }) {
+                        error!("{e}");
+                    }
+                }
+                if let Ok(w) = get(edit_game_dialog) {
+                    if let Err(e) = w.upgrade_in_event_loop(move |dialog| {
+                        dialog.set_java_ids(java::ui_java_ids(&list));
+                    }) {
+                        error!("{e}");
+                    }
+                }
+            }
+            UIUpdate::SetJvmPresets(list) => {
+                // 设置页面和两个对话框都需要，未打开的对话框忽略
+                let list_clone = list.clone();
+                if let Err(e) = ui_weak.upgrade_in_event_loop(move |ui| {
+                    ui.set_jvm_presets(ui_jvm_preset_list(&list_clone));
+                }) {
+                    error!("{e}")
+                }
+                if let Ok(w) = get(add_game_dialog) {
+                    let list = list.clone();
+                    if let Err(e) = w.upgrade_in_event_loop(move |dialog| {
+                        dialog.set_jvm_presets(ui_jvm_preset_list(&list));
+                    }) {
+                        error!("{e}");
+                    }
+                }
+                if let Ok(w) = get(edit_game_dialog) {
+                    if let Err(e) = w.upgrade_in_event_loop(move |dialog| {
+                        dialog.set_jvm_presets(ui_jvm_preset_list(&list));
+                    }) {
+                        error!("{e}");
+                    }
+                }
+            }
             UIUpdate::SetOfflineAccount(account) => match get(login_dialog) {
                 Ok(w) => {
                     if let Err(e) = w.upgrade_in_event_loop(move |dialog| {
@@ -440,6 +653,13 @@ impl AppWindow {
                     error!("{e}");
                 }
             },
+            UIUpdate::SetRunningGames(list) => {
+                if let Err(e) = ui_weak.upgrade_in_event_loop(move |ui| {
+                    ui.set_running_model(home::ui_running_list(&list));
+                }) {
+                    error!("{e}")
+                }
+            }
             UIUpdate::SetVersion(version) => {
                 if let Err(e) = ui_weak.upgrade_in_event_loop(move |ui| {
                     ui.set_version(version.into());
@@ -447,6 +667,15 @@ impl AppWindow {
                     error!("{e}")
                 }
             }
+            UIUpdate::ShowLaunchScript(path, script) => {
+                if let Err(e) = slint::invoke_from_event_loop(move || {
+                    if let Err(e) = game::launch_script_dialog(path, script) {
+                        error!("{e}");
+                    }
+                }) {
+                    error!("{e}");
+                }
+            }
             UIUpdate::Quit => {
                 if let Err(e) = ui_weak.upgrade_in_event_loop(|ui| {
                     ui.hide().unwrap();
@@ -466,6 +695,18 @@ impl AppWindow {
                     error!("{e}");
                 }
             },
+            UIUpdate::QuitAddJavaDialog => match get(add_java_dialog) {
+                Ok(w) => {
+                    if let Err(e) = w.upgrade_in_event_loop(move |dialog| {
+                        dialog.hide().unwrap();
+                    }) {
+                        error!("{e}");
+                    }
+                }
+                Err(e) => {
+                    error!("{e}");
+                }
+            },
             UIUpdate::QuitEditGameDialog => match get(edit_game_dialog) {
                 Ok(w) => {
                     if let Err(e) = w.upgrade_in_event_loop(move |dialog| {
@@ -497,6 +738,11 @@ impl AppWindow {
         self.update_sender.clone()
     }
 
+    /// the runtime uses it to send commands from the ask boxes
+    pub fn get_cmd_sender(&self) -> tokio::sync::mpsc::UnboundedSender<UICommand> {
+        self.cmd_sender.clone()
+    }
+
     pub fn take_cmd_receiver(&mut self) -> Option<tokio::sync::mpsc::UnboundedReceiver<UICommand>> {
         self.cmd_receiver.take()
     }
@@ -2,16 +2,19 @@
 
 use log::error;
 use slint::{ComponentHandle, ModelRc, StandardListViewItem, VecModel};
+use std::collections::BTreeMap;
 use std::rc;
 use tokio::sync::mpsc::UnboundedSender;
+use utils::shell;
 
 use crate::app_window::UICommand;
 use crate::msg_box;
-use crate::ui::{self, AddGameDialog, EditGameDialog};
+use crate::ui::{self, AddGameDialog, EditGameDialog, LaunchScriptDialog};
 
 pub enum ModType {
     Fabric,
     Forge,
+    NeoForge,
 }
 
 #[derive(Clone, PartialEq, Eq)]
@@ -37,6 +40,12 @@ pub struct Forge {
     pub modified: String,
 }
 
+/// NeoForge信息
+#[derive(Clone)]
+pub struct NeoForge {
+    pub version: String,
+}
+
 /// MC信息，下载用
 pub struct MCDL {
     pub game_type: MCType,
@@ -46,14 +55,21 @@ pub struct MCDL {
 
 /// MC配置
 pub struct MCConfig {
+    pub auto_memory: bool,
     pub description: String,
+    pub env: BTreeMap<String, String>,
     pub game_args: Vec<String>,
     pub height: u32,
+    /// 受管理的Java的id，为空时使用java_path
+    pub java_id: String,
     pub java_path: String,
     pub jvm_args: Vec<String>,
+    pub jvm_preset: String,
+    pub post_exit: Vec<String>,
+    pub pre_launch: Vec<String>,
     pub separated: bool,
     pub width: u32,
-    pub wrapper: String,
+    pub wrapper: Vec<Vec<String>>,
     pub xms: String,
     pub xmx: String,
 }
@@ -62,6 +78,10 @@ pub struct MCConfig {
 pub struct MCInfo {
     pub description: String,
     pub game_type: MCType,
+    /// 最后游戏时间，从未游戏时为空
+    pub last_played: String,
+    /// 总游戏时长
+    pub play_time: String,
     pub version: String,
 }
 
@@ -76,29 +96,76 @@ impl MCType {
     }
 }
 
-impl From<ui::MCConfig> for MCConfig {
-    fn from(value: ui::MCConfig) -> Self {
-        let game_args = if value.game_args.is_empty() {
-            Vec::new()
-        } else {
-            value.game_args.split(" ").map(|s| s.to_string()).collect()
-        };
+/// 多行文本转为列表，每行一项，忽略空行
+pub(crate) fn split_lines(s: &str) -> Vec<String> {
+    s.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_string())
+        .collect()
+}
 
-        let jvm_args = if value.jvm_args.is_empty() {
-            Vec::new()
-        } else {
-            value.jvm_args.split(" ").map(|s| s.to_string()).collect()
-        };
+/// 多行文本转为封装器链，每行一个封装器，按shell规则拆分
+pub(crate) fn split_wrapper(s: &str) -> Result<Vec<Vec<String>>, shell::SplitError> {
+    let mut wrappers = Vec::new();
+    for line in s.lines() {
+        let wrapper = shell::split(line)?;
+        if !wrapper.is_empty() {
+            wrappers.push(wrapper);
+        }
+    }
+    Ok(wrappers)
+}
+
+/// 封装器链转为多行文本
+pub(crate) fn join_wrapper(wrapper: &[Vec<String>]) -> String {
+    wrapper
+        .iter()
+        .map(|w| shell::join(w))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
 
+/// 多行文本转为环境变量，每行一个KEY=VALUE，忽略格式错误的行
+pub(crate) fn split_env(s: &str) -> BTreeMap<String, String> {
+    s.lines()
+        .filter_map(|line| {
+            let (k, v) = line.trim_start().split_once('=')?;
+            let k = k.trim();
+            if k.is_empty() {
+                None
+            } else {
+                Some((k.to_string(), v.to_string()))
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn join_env(env: &BTreeMap<String, String>) -> String {
+    env.iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+impl From<ui::MCConfig> for MCConfig {
+    fn from(value: ui::MCConfig) -> Self {
+        // 保存前已由check_config检查
         Self {
+            auto_memory: value.auto_memory,
             description: value.description.into(),
-            game_args,
+            env: split_env(&value.env),
+            game_args: shell::split(&value.game_args).unwrap_or_default(),
             height: value.height as u32,
+            java_id: value.java_id.into(),
             java_path: value.java_path.into(),
-            jvm_args,
+            jvm_args: shell::split(&value.jvm_args).unwrap_or_default(),
+            jvm_preset: value.jvm_preset.into(),
+            post_exit: split_lines(&value.post_exit),
+            pre_launch: split_lines(&value.pre_launch),
             separated: value.separated,
             width: value.width as u32,
-            wrapper: value.wrapper.into(),
+            wrapper: split_wrapper(&value.wrapper).unwrap_or_default(),
             xms: value.xms.into(),
             xmx: value.xmx.into(),
         }
@@ -107,35 +174,55 @@ impl From<ui::MCConfig> for MCConfig {
 
 impl From<MCConfig> for ui::MCConfig {
     fn from(value: MCConfig) -> Self {
-        let mut game_args = String::new();
-        for arg in value.game_args {
-            game_args += &arg;
-            game_args += " ";
-        }
-        game_args.pop();
-
-        let mut jvm_args = String::new();
-        for arg in value.jvm_args {
-            jvm_args += &arg;
-            jvm_args += " ";
-        }
-        jvm_args.pop();
-
         Self {
+            auto_memory: value.auto_memory,
             description: value.description.into(),
-            game_args: game_args.into(),
+            env: join_env(&value.env).into(),
+            game_args: shell::join(&value.game_args).into(),
             height: value.height as i32,
+            java_id: value.java_id.into(),
             java_path: value.java_path.into(),
-            jvm_args: jvm_args.into(),
+            jvm_args: shell::join(&value.jvm_args).into(),
+            jvm_preset: value.jvm_preset.into(),
+            post_exit: value.post_exit.join("\n").into(),
+            pre_launch: value.pre_launch.join("\n").into(),
             separated: value.separated,
             width: value.width as i32,
-            wrapper: value.wrapper.into(),
+            wrapper: join_wrapper(&value.wrapper).into(),
             xms: value.xms.into(),
             xmx: value.xmx.into(),
         }
     }
 }
 
+/// 检查参数和封装器的引号是否匹配，以及环境变量的格式，返回错误信息，没有错误时返回空字符串
+fn check_config(jvm_args: &str, game_args: &str, wrapper: &str, env: &str) -> String {
+    if let Err(e) = shell::split(jvm_args) {
+        return format!("JVM args: {e}");
+    }
+    if let Err(e) = shell::split(game_args) {
+        return format!("Game args: {e}");
+    }
+    check_wrapper_env(wrapper, env)
+}
+
+/// 检查封装器和环境变量，返回错误信息，没有错误时返回空字符串
+pub(crate) fn check_wrapper_env(wrapper: &str, env: &str) -> String {
+    if let Err(e) = split_wrapper(wrapper) {
+        return format!("Wrappers: {e}");
+    }
+    for (i, line) in env.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match line.trim_start().split_once('=') {
+            Some((k, _)) if !k.trim().is_empty() => {}
+            _ => return format!("Environment variables: line {} is not KEY=VALUE.", i + 1),
+        }
+    }
+    String::new()
+}
+
 /// 获取ui用的download_fabric_list
 pub fn ui_fabric_list(fabric_list: &Vec<Fabric>) -> ModelRc<ModelRc<StandardListViewItem>> {
     let mut ui_fabric_list: Vec<ModelRc<StandardListViewItem>> = Vec::new();
@@ -166,15 +253,37 @@ pub fn ui_forge_list(forge_list: &Vec<Forge>) -> ModelRc<ModelRc<StandardListVie
     ModelRc::from(rc::Rc::new(VecModel::from(ui_forge_list)))
 }
 
+/// 获取ui用的download_neoforge_list
+pub fn ui_neoforge_list(neoforge_list: &Vec<NeoForge>) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let mut ui_neoforge_list: Vec<ModelRc<StandardListViewItem>> = Vec::new();
+    for neoforge in neoforge_list {
+        let version = StandardListViewItem::from(neoforge.version.as_str());
+        let model: rc::Rc<VecModel<StandardListViewItem>> = rc::Rc::new(VecModel::from(vec![
+            version.into(),
+            StandardListViewItem::default(),
+        ]));
+        let row: ModelRc<StandardListViewItem> = ModelRc::from(model);
+        ui_neoforge_list.push(row);
+    }
+    ModelRc::from(rc::Rc::new(VecModel::from(ui_neoforge_list)))
+}
+
 /// 获取ui用的game_list
 pub fn ui_game_list(game_list: &Vec<MCInfo>) -> ModelRc<ModelRc<StandardListViewItem>> {
     let mut ui_game_list: Vec<ModelRc<StandardListViewItem>> = Vec::new();
     for game in game_list {
         let version = StandardListViewItem::from(game.version.as_str());
         let game_type = StandardListViewItem::from(game.game_type.as_str());
+        let last_played = StandardListViewItem::from(game.last_played.as_str());
+        let play_time = StandardListViewItem::from(game.play_time.as_str());
         let description = StandardListViewItem::from(game.description.as_str());
-        let model: rc::Rc<VecModel<StandardListViewItem>> =
-            rc::Rc::from(VecModel::from(vec![version, game_type, description]));
+        let model: rc::Rc<VecModel<StandardListViewItem>> = rc::Rc::from(VecModel::from(vec![
+            version,
+            game_type,
+            last_played,
+            play_time,
+            description,
+        ]));
         let row: ModelRc<StandardListViewItem> = ModelRc::from(model);
         ui_game_list.push(row);
     }
@@ -187,6 +296,12 @@ pub fn ui_combo_box_list(game_list: &Vec<MCInfo>) -> ModelRc<slint::SharedString
     ModelRc::from(rc::Rc::new(VecModel::from(list)))
 }
 
+/// 获取ui用的JVM参数预设列表
+pub fn ui_jvm_preset_list(presets: &Vec<String>) -> ModelRc<slint::SharedString> {
+    let list: Vec<slint::SharedString> = presets.iter().map(|v| v.clone().into()).collect();
+    ModelRc::from(rc::Rc::new(VecModel::from(list)))
+}
+
 /// 获取ui用的download_game_list
 pub fn ui_game_dl_list(game_list: &Vec<MCDL>) -> ModelRc<ModelRc<StandardListViewItem>> {
     let mut ui_game_list: Vec<ModelRc<StandardListViewItem>> = Vec::new();
@@ -207,6 +322,10 @@ pub fn add_game_dialog(
     let ui = AddGameDialog::new()?;
     let ui_weak = ui.as_weak();
 
+    ui.on_check_config(|jvm_args, game_args, wrapper, env| {
+        check_config(&jvm_args, &game_args, &wrapper, &env).into()
+    });
+
     let tx_clone = tx.clone();
     ui.on_get_default_game_config(move || {
         if let Err(e) = tx_clone.send(UICommand::GetAddGameDefault) {
@@ -241,6 +360,7 @@ pub fn add_game_dialog(
         let msg = match filter {
             1 => UICommand::GetAddModListForge(t, index as u32),
             2 => UICommand::GetAddModListFabric(t, index as u32),
+            3 => UICommand::GetAddModListNeoForge(t, index as u32),
             _ => {
                 error!("Unexpected mod type {filter}");
                 return;
@@ -264,6 +384,7 @@ pub fn add_game_dialog(
         let mod_filter = match mod_type {
             1 => Some(ModType::Forge),
             2 => Some(ModType::Fabric),
+            3 => Some(ModType::NeoForge),
             _ => None,
         };
         tx_clone
@@ -289,6 +410,9 @@ pub fn add_game_dialog(
     ui.show()?;
     tx.send(UICommand::GetAddGameDefault).unwrap();
     tx.send(UICommand::GetAddGameList(None)).unwrap();
+    tx.send(UICommand::GetDetectedWrappers).unwrap();
+    tx.send(UICommand::GetJavaList).unwrap();
+    tx.send(UICommand::GetJvmPresets).unwrap();
     Ok(ui_weak)
 }
 
@@ -299,6 +423,10 @@ pub fn edit_game_dialog(
     let ui = EditGameDialog::new()?;
     let ui_weak = ui.as_weak();
 
+    ui.on_check_config(|jvm_args, game_args, wrapper, env| {
+        check_config(&jvm_args, &game_args, &wrapper, &env).into()
+    });
+
     let tx_clone = tx.clone();
     ui.on_del_game(move || {
         let tx = tx_clone.clone();
@@ -318,6 +446,20 @@ pub fn edit_game_dialog(
         }
     });
 
+    let tx_clone = tx.clone();
+    ui.on_export_script(move |redact_token| {
+        if let Err(e) = tx_clone.send(UICommand::ExportLaunchScript(index, redact_token)) {
+            error!("{e}");
+        }
+    });
+
+    let tx_clone = tx.clone();
+    ui.on_repair_game(move || {
+        if let Err(e) = tx_clone.send(UICommand::RepairGame(index)) {
+            error!("{e}");
+        }
+    });
+
     let ui_weak_clone = ui_weak.clone();
     ui.on_cancel_clicked(move || {
         if let Some(ui) = ui_weak_clone.upgrade() {
@@ -330,5 +472,30 @@ pub fn edit_game_dialog(
     ui.show()?;
     tx.send(UICommand::GetEditGameConfig(index)).unwrap();
     tx.send(UICommand::GetEditGameVersion(index)).unwrap();
+    tx.send(UICommand::GetDetectedWrappers).unwrap();
+    tx.send(UICommand::GetJavaList).unwrap();
+    tx.send(UICommand::GetJvmPresets).unwrap();
+    Ok(ui_weak)
+}
+
+pub fn launch_script_dialog(
+    path: String,
+    script: String,
+) -> Result<slint::Weak<LaunchScriptDialog>, slint::PlatformError> {
+    let ui = LaunchScriptDialog::new()?;
+    let ui_weak = ui.as_weak();
+    ui.set_path(path.into());
+    ui.set_script(script.into());
+
+    let ui_weak_clone = ui_weak.clone();
+    ui.on_close_clicked(move || {
+        if let Some(ui) = ui_weak_clone.upgrade() {
+            ui.hide().unwrap();
+        } else {
+            error!("Failed to upgrade a weak pointer.");
+        }
+    });
+
+    ui.show()?;
     Ok(ui_weak)
 }
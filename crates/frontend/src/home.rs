@@ -1,8 +1,20 @@
+use slint::{ModelRc, StandardListViewItem, VecModel};
+use std::rc;
+
 use crate::ui;
 
+/// 正在运行的游戏
+pub struct RunningGame {
+    pub account: String,
+    pub pid: String,
+    pub start_time: String,
+    pub version: String,
+}
+
 pub enum State {
     Spare,
     Downloading,
+    Installing,
     Launching,
     LoggingIn,
 }
@@ -11,9 +23,24 @@ impl From<State> for ui::State {
     fn from(value: State) -> Self {
         match value {
             State::Downloading => ui::State::Downloading,
+            State::Installing => ui::State::Installing,
             State::Launching => ui::State::Launching,
             State::LoggingIn => ui::State::LoggingIn,
             State::Spare => ui::State::Spare,
         }
     }
 }
+
+pub fn ui_running_list(list: &Vec<RunningGame>) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let mut ui_list: Vec<ModelRc<StandardListViewItem>> = Vec::new();
+    for game in list {
+        let pid = StandardListViewItem::from(game.pid.as_str());
+        let version = StandardListViewItem::from(game.version.as_str());
+        let account = StandardListViewItem::from(game.account.as_str());
+        let start_time = StandardListViewItem::from(game.start_time.as_str());
+        let model: rc::Rc<VecModel<StandardListViewItem>> =
+            rc::Rc::from(VecModel::from(vec![pid, version, account, start_time]));
+        ui_list.push(ModelRc::from(model));
+    }
+    ModelRc::from(rc::Rc::new(VecModel::from(ui_list)))
+}
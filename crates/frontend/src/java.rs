@@ -0,0 +1,74 @@
+//! Java Page相关
+
+use log::error;
+use slint::{ComponentHandle, ModelRc, SharedString, StandardListViewItem, VecModel};
+use std::rc;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::app_window::UICommand;
+use crate::ui::AddJavaDialog;
+
+/// Java页面中的Java
+#[derive(Clone)]
+pub struct JavaInfo {
+    pub arch: String,
+    pub id: String,
+    pub path: String,
+    pub vendor: String,
+    pub version: String,
+}
+
+pub fn ui_java_list(list: &Vec<JavaInfo>) -> ModelRc<ModelRc<StandardListViewItem>> {
+    let mut ui_list: Vec<ModelRc<StandardListViewItem>> = Vec::new();
+    for java in list {
+        let id = StandardListViewItem::from(java.id.as_str());
+        let version = StandardListViewItem::from(java.version.as_str());
+        let vendor = StandardListViewItem::from(java.vendor.as_str());
+        let arch = StandardListViewItem::from(java.arch.as_str());
+        let path = StandardListViewItem::from(java.path.as_str());
+        let model: rc::Rc<VecModel<StandardListViewItem>> =
+            rc::Rc::from(VecModel::from(vec![id, version, vendor, arch, path]));
+        ui_list.push(ModelRc::from(model));
+    }
+    ModelRc::from(rc::Rc::new(VecModel::from(ui_list)))
+}
+
+/// 游戏对话框中可选的Java id，第一项为空，表示使用Java路径
+pub fn ui_java_ids(list: &[JavaInfo]) -> ModelRc<SharedString> {
+    let mut ids: Vec<SharedString> = vec![SharedString::new()];
+    ids.extend(list.iter().map(|java| SharedString::from(java.id.as_str())));
+    ModelRc::from(rc::Rc::new(VecModel::from(ids)))
+}
+
+/// Adoptium提供的Java主版本，新的在前
+pub fn ui_adoptium_releases(list: &[u8]) -> ModelRc<SharedString> {
+    let list: Vec<SharedString> = list
+        .iter()
+        .rev()
+        .map(|feature| SharedString::from(feature.to_string()))
+        .collect();
+    ModelRc::from(rc::Rc::new(VecModel::from(list)))
+}
+
+pub fn add_java_dialog(
+    tx: UnboundedSender<UICommand>,
+) -> Result<slint::Weak<AddJavaDialog>, slint::PlatformError> {
+    let ui = AddJavaDialog::new()?;
+    let ui_weak = ui.as_weak();
+
+    ui.on_add_java(move |path| {
+        tx.send(UICommand::AddJava(path.into())).unwrap();
+    });
+
+    let ui_weak_clone = ui_weak.clone();
+    ui.on_cancel_clicked(move || {
+        if let Some(ui) = ui_weak_clone.upgrade() {
+            ui.hide().unwrap();
+        } else {
+            error!("Failed to upgrade a weak pointer.");
+        }
+    });
+
+    ui.show()?;
+    Ok(ui_weak)
+}
@@ -2,6 +2,7 @@ pub mod account;
 mod app_window;
 pub mod game;
 pub mod home;
+pub mod java;
 mod msg_box;
 mod settings;
 mod ui;
@@ -9,5 +10,6 @@ mod ui;
 pub use app_window::AppWindow;
 pub use app_window::UICommand;
 pub use app_window::UIUpdate;
+pub use msg_box::AskID;
 pub use msg_box::MsgID;
 pub use settings::{Config, ConfigDL, ConfigGeneral, ConfigMC};
@@ -7,20 +7,31 @@ pub enum MsgID {
     BothNotSelected,            // On launch, not select account and game
     CopyUserCodeFailed(String), // On login, copy usercode failed, with user code provided
     DLFailed(String),           // Download failed, with reason
+    GameCrashed(String),        // Game exited abnormally, with the diagnosis
     GameNotSelected,            // On launch, not select game
+    GameRunning,                // On launch, the game directory is in use
+    HookFailed(String),         // On launch, a pre-launch command failed, with the command
+    InstallFailed(String),      // On add game, failed to install the mod loader, with reason
+    InvalidJava(String),        // On add Java, no Java installation is found, with the path
+    InvalidMemory(String),      // On launch, xms or xmx is invalid, with reason
+    JavaWarning(String),        // On launch, the Java may not run the game, with the problems
     LoadAccFailed(String),      // On init, failed to load account list, with reason
     LoadConfigFailed(String),   // On init, failed to load config, with reason
     LoadGameFailed(String),     // On init, failed to load game list, with reason
     LoginFailed,                // On launch, failed to login, with reason
     LaunchFailed,               // On launch, failed to launch game, with reason
     OAuthFailed,                // On add account, OAuth Error, with reason
+    RepairFinished(String),     // Game files verified, with the report
     VersionExists,              // On add game, version already exists
     WeakPtrError,               // Failed to upgrade a weak pointer
 }
 
 pub enum AskID {
-    DelAccConfirm,  // Confirm to delete an account
-    DelGameConfirm, // Confirm to delete a game
+    DelAccConfirm,            // Confirm to delete an account
+    DelGameConfirm,           // Confirm to delete a game
+    JavaDLConfirm(String),    // Confirm to download the Java required to launch, with its version
+    KillGameConfirm,          // Confirm to kill a running game
+    WrongJavaConfirm(String), // Confirm to launch without a compatible Java, with the required version
 }
 
 fn ui_msg(id: MsgID) -> (ui::MsgID, Option<String>) {
@@ -29,13 +40,21 @@ fn ui_msg(id: MsgID) -> (ui::MsgID, Option<String>) {
         MsgID::BothNotSelected => (ui::MsgID::BothNotSelected, None),
         MsgID::CopyUserCodeFailed(s) => (ui::MsgID::CopyUserCodeFailed, Some(s)),
         MsgID::DLFailed(s) => (ui::MsgID::DLFailed, Some(s)),
+        MsgID::GameCrashed(s) => (ui::MsgID::GameCrashed, Some(s)),
         MsgID::GameNotSelected => (ui::MsgID::GameNotSelected, None),
+        MsgID::GameRunning => (ui::MsgID::GameRunning, None),
+        MsgID::HookFailed(s) => (ui::MsgID::HookFailed, Some(s)),
+        MsgID::InstallFailed(s) => (ui::MsgID::InstallFailed, Some(s)),
+        MsgID::InvalidJava(s) => (ui::MsgID::InvalidJava, Some(s)),
+        MsgID::InvalidMemory(s) => (ui::MsgID::InvalidMemory, Some(s)),
+        MsgID::JavaWarning(s) => (ui::MsgID::JavaWarning, Some(s)),
         MsgID::LoadAccFailed(s) => (ui::MsgID::LoadAccFailed, Some(s)),
         MsgID::LoadConfigFailed(s) => (ui::MsgID::LoadConfigFailed, Some(s)),
         MsgID::LoadGameFailed(s) => (ui::MsgID::LoadGameFailed, Some(s)),
         MsgID::LoginFailed => (ui::MsgID::LoginFailed, None),
         MsgID::LaunchFailed => (ui::MsgID::LaunchFailed, None),
         MsgID::OAuthFailed => (ui::MsgID::OAuthFailed, None),
+        MsgID::RepairFinished(s) => (ui::MsgID::RepairFinished, Some(s)),
         MsgID::VersionExists => (ui::MsgID::VersionExists, None),
         MsgID::WeakPtrError => (ui::MsgID::WeakPtrError, None),
     }
@@ -45,6 +64,9 @@ fn ui_ask(id: AskID) -> (ui::AskID, Option<String>) {
     match id {
         AskID::DelAccConfirm => (ui::AskID::DelAccConfirm, None),
         AskID::DelGameConfirm => (ui::AskID::DelGameConfirm, None),
+        AskID::JavaDLConfirm(s) => (ui::AskID::JavaDLConfirm, Some(s)),
+        AskID::KillGameConfirm => (ui::AskID::KillGameConfirm, None),
+        AskID::WrongJavaConfirm(s) => (ui::AskID::WrongJavaConfirm, Some(s)),
     }
 }
 
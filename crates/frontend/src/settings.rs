@@ -1,5 +1,8 @@
 //! Settings Page
 
+use std::collections::BTreeMap;
+
+use crate::game::{join_env, join_wrapper, split_env, split_lines, split_wrapper};
 use crate::ui;
 
 #[derive(Clone)]
@@ -8,10 +11,14 @@ pub struct ConfigGeneral {
     pub close_after_launch: bool,
     /// .minecraft路径
     pub game_path: String,
+    /// 使用启动器提供的日志配置（XML格式输出）
+    pub launcher_log_config: bool,
 }
 
 #[derive(Clone)]
 pub struct ConfigDL {
+    /// Adoptium API，用于下载Java
+    pub adoptium_source: String,
     /// assets下载源
     pub assets_source: String,
     /// 下载时的最大并发数量
@@ -24,24 +31,36 @@ pub struct ConfigDL {
     pub game_source: String,
     /// libraries下载源
     pub libraries_source: String,
+    /// NeoForge下载源
+    pub neoforge_source: String,
 }
 
 #[derive(Clone)]
 pub struct ConfigMC {
+    /// 新版本默认自动设置内存
+    pub auto_memory: bool,
+    /// 所有版本使用的环境变量
+    pub env: BTreeMap<String, String>,
     /// 默认游戏窗口高度
     pub height: u32,
     /// java可执行文件路径
     pub java_path: String,
+    /// 默认JVM参数预设名
+    pub jvm_preset: String,
     /// 默认游戏窗口宽度
     pub width: u32,
     /// 封装器
-    pub wrapper: String,
+    pub wrapper: Vec<Vec<String>>,
     /// 默认JVM最小内存
     pub xms: String,
     /// 默认JVM最大内存
     pub xmx: String,
     /// MC Path
     pub path: String,
+    /// 所有版本退出后执行的命令
+    pub post_exit: Vec<String>,
+    /// 所有版本启动前执行的命令
+    pub pre_launch: Vec<String>,
 }
 
 /// 启动器配置
@@ -58,12 +77,14 @@ pub struct Config {
 impl From<ui::ConfigDL> for ConfigDL {
     fn from(value: ui::ConfigDL) -> Self {
         Self {
+            adoptium_source: value.adoptium_source.into(),
             assets_source: value.assets_source.into(),
             concurrency: value.concurrency as u32,
             fabric_source: value.fabric_source.into(),
             forge_source: value.forge_source.into(),
             game_source: value.game_source.into(),
             libraries_source: value.libraries_source.into(),
+            neoforge_source: value.neoforge_source.into(),
         }
     }
 }
@@ -73,6 +94,7 @@ impl From<ui::ConfigGeneral> for ConfigGeneral {
         Self {
             close_after_launch: value.close_after_launch,
             game_path: value.game_path.into(),
+            launcher_log_config: value.launcher_log_config,
         }
     }
 }
@@ -82,11 +104,17 @@ impl From<ui::ConfigMC> for ConfigMC {
         Self {
             height: value.height as u32,
             java_path: value.java_path.into(),
+            jvm_preset: value.jvm_preset.into(),
             width: value.width as u32,
-            wrapper: value.wrapper.into(),
+            // 保存前已由check_wrapper_env检查
+            wrapper: split_wrapper(&value.wrapper).unwrap_or_default(),
             xms: value.xms.into(),
             xmx: value.xmx.into(),
             path: value.path.into(),
+            auto_memory: value.auto_memory,
+            env: split_env(&value.env),
+            post_exit: split_lines(&value.post_exit),
+            pre_launch: split_lines(&value.pre_launch),
         }
     }
 }
@@ -104,12 +132,14 @@ impl From<ui::Config> for Config {
 impl From<ConfigDL> for ui::ConfigDL {
     fn from(value: ConfigDL) -> Self {
         Self {
+            adoptium_source: value.adoptium_source.into(),
             assets_source: value.assets_source.into(),
             concurrency: value.concurrency as i32,
             fabric_source: value.fabric_source.into(),
             forge_source: value.forge_source.into(),
             game_source: value.game_source.into(),
             libraries_source: value.libraries_source.into(),
+            neoforge_source: value.neoforge_source.into(),
         }
     }
 }
@@ -119,6 +149,7 @@ impl From<ConfigGeneral> for ui::ConfigGeneral {
         Self {
             close_after_launch: value.close_after_launch,
             game_path: value.game_path.into(),
+            launcher_log_config: value.launcher_log_config,
         }
     }
 }
@@ -128,11 +159,16 @@ impl From<ConfigMC> for ui::ConfigMC {
         Self {
             height: value.height as i32,
             java_path: value.java_path.into(),
+            jvm_preset: value.jvm_preset.into(),
             width: value.width as i32,
-            wrapper: value.wrapper.into(),
+            wrapper: join_wrapper(&value.wrapper).into(),
             xms: value.xms.into(),
             xmx: value.xmx.into(),
             path: value.path.into(),
+            auto_memory: value.auto_memory,
+            env: join_env(&value.env).into(),
+            post_exit: value.post_exit.join("\n").into(),
+            pre_launch: value.pre_launch.join("\n").into(),
         }
     }
 }
@@ -0,0 +1,299 @@
+//! JDK and JRE builds from an Adoptium compatible API, e.g. https://api.adoptium.net
+
+use flate2::read::GzDecoder;
+use log::{error, info};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs::{File, create_dir_all, exists, remove_dir_all, remove_file, rename};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::java::JavaInstallation;
+
+pub enum AdoptiumError {
+    DataInvalid,
+    /// no build for the major version on this platform
+    NotAvailable(u8),
+    /// the sha256 of the downloaded package does not match, with the package path
+    ChecksumMismatch(String),
+    /// the unpacked package is not a java home
+    InvalidPackage(String),
+    DeserializeError(serde_json::Error),
+    IOError(io::Error),
+    ReqwestError(reqwest::Error),
+    ZipError(zip::result::ZipError),
+}
+
+impl From<serde_json::Error> for AdoptiumError {
+    fn from(err: serde_json::Error) -> Self {
+        AdoptiumError::DeserializeError(err)
+    }
+}
+
+impl From<io::Error> for AdoptiumError {
+    fn from(err: io::Error) -> Self {
+        AdoptiumError::IOError(err)
+    }
+}
+
+impl From<reqwest::Error> for AdoptiumError {
+    fn from(err: reqwest::Error) -> Self {
+        AdoptiumError::ReqwestError(err)
+    }
+}
+
+impl From<zip::result::ZipError> for AdoptiumError {
+    fn from(err: zip::result::ZipError) -> Self {
+        AdoptiumError::ZipError(err)
+    }
+}
+
+impl std::fmt::Display for AdoptiumError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AdoptiumError::DataInvalid => write!(f, "Adoptium API response is invalid"),
+            AdoptiumError::NotAvailable(feature) => {
+                write!(f, "Java {feature} is not available for this platform")
+            }
+            AdoptiumError::ChecksumMismatch(s) => write!(f, "The checksum of {s} does not match"),
+            AdoptiumError::InvalidPackage(s) => write!(f, "{s} does not contain a Java"),
+            AdoptiumError::DeserializeError(err) => write!(f, "{err}"),
+            AdoptiumError::IOError(err) => write!(f, "{err}"),
+            AdoptiumError::ReqwestError(err) => write!(f, "{err}"),
+            AdoptiumError::ZipError(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ImageType {
+    Jdk,
+    Jre,
+}
+
+impl ImageType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ImageType::Jdk => "jdk",
+            ImageType::Jre => "jre",
+        }
+    }
+}
+
+/// a downloadable build in the API
+pub struct AdoptiumPackage {
+    /// e.g. jdk-21.0.1+12
+    pub release_name: String,
+    /// e.g. 21.0.1+12-LTS
+    pub version: String,
+    pub image_type: ImageType,
+    /// the file name, e.g. OpenJDK21U-jre_x64_linux_hotspot_21.0.1_12.tar.gz
+    pub name: String,
+    pub link: String,
+    /// sha256 of the package
+    pub checksum: String,
+    pub size: u64,
+}
+
+/// the os name in the API, None if there is no build for it
+pub fn get_os() -> Option<&'static str> {
+    match std::env::consts::OS {
+        "linux" => Some("linux"),
+        "macos" => Some("mac"),
+        "windows" => Some("windows"),
+        _ => None,
+    }
+}
+
+/// the architecture name of this machine in the API
+pub fn get_arch() -> Option<&'static str> {
+    match std::env::consts::ARCH {
+        "x86_64" => Some("x64"),
+        "x86" => Some("x86"),
+        "aarch64" => Some("aarch64"),
+        "arm" => Some("arm"),
+        "powerpc64" => Some("ppc64le"),
+        "s390x" => Some("s390x"),
+        _ => None,
+    }
+}
+
+/// the major versions with builds, e.g. [8, 11, 17, 21]
+pub async fn list_releases(source: &str) -> Result<Vec<u8>, AdoptiumError> {
+    let text = reqwest::get(format!("{source}/v3/info/available_releases"))
+        .await?
+        .text()
+        .await?;
+    let json = serde_json::from_str::<Value>(&text)?;
+    json["available_releases"]
+        .as_array()
+        .ok_or(AdoptiumError::DataInvalid)?
+        .iter()
+        .map(|feature| feature.as_u64().map(|feature| feature as u8))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or(AdoptiumError::DataInvalid)
+}
+
+/// the latest builds of the major version for this os and the architecture,
+/// arch is the name in the API like x64 and aarch64, see get_arch
+pub async fn list_packages(
+    source: &str,
+    feature: u8,
+    arch: &str,
+    image_type: ImageType,
+) -> Result<Vec<AdoptiumPackage>, AdoptiumError> {
+    let os = get_os().ok_or(AdoptiumError::NotAvailable(feature))?;
+    let url = format!(
+        "{source}/v3/assets/latest/{feature}/hotspot?architecture={arch}&image_type={}&os={os}",
+        image_type.as_str()
+    );
+    let response = reqwest::get(url).await?;
+    // no build for the os, the architecture or the image type
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(Vec::new());
+    }
+    let json = serde_json::from_str::<Value>(&response.text().await?)?;
+
+    let mut packages = Vec::new();
+    for node in json.as_array().ok_or(AdoptiumError::DataInvalid)? {
+        let package = &node["binary"]["package"];
+        let field = |value: &Value| {
+            value
+                .as_str()
+                .map(String::from)
+                .ok_or(AdoptiumError::DataInvalid)
+        };
+        packages.push(AdoptiumPackage {
+            release_name: field(&node["release_name"])?,
+            version: field(&node["version"]["openjdk_version"])?,
+            image_type,
+            name: field(&package["name"])?,
+            link: field(&package["link"])?,
+            checksum: field(&package["checksum"])?,
+            size: package["size"].as_u64().unwrap_or_default(),
+        });
+    }
+    Ok(packages)
+}
+
+/// the first build of the major version for this machine, a JRE is preferred
+pub async fn find_package(source: &str, feature: u8) -> Result<AdoptiumPackage, AdoptiumError> {
+    let arch = get_arch().ok_or(AdoptiumError::NotAvailable(feature))?;
+    for image_type in [ImageType::Jre, ImageType::Jdk] {
+        let packages = list_packages(source, feature, arch, image_type).await?;
+        // the installers like .msi and .pkg can not be unpacked
+        if let Some(package) = packages
+            .into_iter()
+            .find(|package| package.name.ends_with(".tar.gz") || package.name.ends_with(".zip"))
+        {
+            return Ok(package);
+        }
+    }
+    Err(AdoptiumError::NotAvailable(feature))
+}
+
+fn check_sha256(path: &str, checksum: &str) -> io::Result<bool> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    let hash = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    Ok(hash.eq_ignore_ascii_case(checksum))
+}
+
+/// the first directory in the path of an entry
+fn top_dir(path: &Path) -> Option<String> {
+    match path.components().find(|c| *c != Component::CurDir)? {
+        Component::Normal(dir) => Some(dir.to_string_lossy().to_string()),
+        _ => None,
+    }
+}
+
+/// unpack a .tar.gz and get the top directory
+fn unpack_tar_gz(archive_path: &str, dir: &str) -> Result<Option<String>, AdoptiumError> {
+    let mut archive = tar::Archive::new(GzDecoder::new(File::open(archive_path)?));
+    let mut top = None;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if top.is_none() {
+            top = top_dir(&entry.path()?);
+        }
+        entry.unpack_in(dir)?;
+    }
+    Ok(top)
+}
+
+/// unpack a .zip and get the top directory
+fn unpack_zip(archive_path: &str, dir: &str) -> Result<Option<String>, AdoptiumError> {
+    let mut archive = zip::ZipArchive::new(File::open(archive_path)?)?;
+    let top = archive
+        .file_names()
+        .next()
+        .and_then(|name| top_dir(Path::new(name)));
+    archive.extract(dir)?;
+    Ok(top)
+}
+
+/// the Java home in an unpacked package, the builds for macOS are bundles
+fn java_home(top: &Path) -> PathBuf {
+    let bundle_home = top.join("Contents/Home");
+    if bundle_home.is_dir() {
+        bundle_home
+    } else {
+        top.to_path_buf()
+    }
+}
+
+/// unpack the package into temp_dir and check it, returns the top directory
+fn unpack_package(archive_path: &str, temp_dir: &str) -> Result<String, AdoptiumError> {
+    let top = if archive_path.ends_with(".zip") {
+        unpack_zip(archive_path, temp_dir)?
+    } else {
+        unpack_tar_gz(archive_path, temp_dir)?
+    };
+    let invalid = || AdoptiumError::InvalidPackage(archive_path.to_string());
+    let top = top.ok_or_else(invalid)?;
+    let home = java_home(&Path::new(temp_dir).join(&top));
+    let java = JavaInstallation::new(home.to_string_lossy().to_string()).map_err(|_| invalid())?;
+    java.get_java_path().map_err(|_| invalid())?;
+    Ok(top)
+}
+
+/// check and unpack the downloaded package into the runtimes directory,
+/// the package is removed after unpacking, and nothing is left if it fails
+pub fn install_package(
+    runtimes_dir: &str,
+    archive_path: &str,
+    checksum: &str,
+) -> Result<JavaInstallation, AdoptiumError> {
+    if !check_sha256(archive_path, checksum)? {
+        remove_file(archive_path)?;
+        return Err(AdoptiumError::ChecksumMismatch(archive_path.to_string()));
+    }
+
+    info!("Unpack {archive_path} into {runtimes_dir}.");
+    create_dir_all(runtimes_dir)?;
+    // unpack into a temporary directory first, so a failure does not leave a broken Java
+    let temp_dir = archive_path.to_string() + ".unpack";
+    if exists(&temp_dir)? {
+        remove_dir_all(&temp_dir)?;
+    }
+    let result = unpack_package(archive_path, &temp_dir).and_then(|top| {
+        let home = Path::new(runtimes_dir).join(&top);
+        // the same build is already installed
+        if !home.exists() {
+            rename(Path::new(&temp_dir).join(&top), &home)?;
+        }
+        Ok(home)
+    });
+    if let Err(e) = remove_dir_all(&temp_dir) {
+        error!("Failed to remove {temp_dir}: {e}");
+    }
+    remove_file(archive_path)?;
+
+    let home = java_home(&result?);
+    JavaInstallation::new(home.to_string_lossy().to_string())
+        .map_err(|_| AdoptiumError::InvalidPackage(archive_path.to_string()))
+}
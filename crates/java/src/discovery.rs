@@ -0,0 +1,127 @@
+//! Find the java installations on the system
+
+use std::fs::{canonicalize, read_dir};
+use std::path::{Path, PathBuf};
+
+use crate::java::JavaInstallation;
+use crate::runtime::get_runtime_home;
+
+/// the executable name of java
+const JAVA_EXECUTABLE: &str = if cfg!(target_os = "windows") {
+    "java.exe"
+} else {
+    "java"
+};
+
+/// directories whose children are java homes
+fn java_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if cfg!(target_os = "windows") {
+        for dir in ["C:/Program Files", "C:/Program Files (x86)"] {
+            for vendor in [
+                "Java",
+                "Eclipse Adoptium",
+                "Eclipse Foundation",
+                "Microsoft",
+                "Zulu",
+                "BellSoft",
+                "Amazon Corretto",
+            ] {
+                dirs.push(Path::new(dir).join(vendor));
+            }
+        }
+    } else if cfg!(target_os = "macos") {
+        dirs.push(PathBuf::from("/Library/Java/JavaVirtualMachines"));
+    } else {
+        dirs.push(PathBuf::from("/usr/lib/jvm"));
+        dirs.push(PathBuf::from("/usr/lib64/jvm"));
+        dirs.push(PathBuf::from("/opt"));
+        dirs.push(PathBuf::from("/opt/java"));
+    }
+
+    if let Some(home) = std::env::home_dir() {
+        if cfg!(target_os = "macos") {
+            dirs.push(home.join("Library/Java/JavaVirtualMachines"));
+        }
+        // SDKMAN!, IntelliJ IDEA and asdf
+        dirs.push(home.join(".sdkman/candidates/java"));
+        dirs.push(home.join(".jdks"));
+        dirs.push(home.join(".asdf/installs/java"));
+        // mise, whose data directory can be changed
+        match std::env::var_os("MISE_DATA_DIR") {
+            Some(mise_dir) => dirs.push(Path::new(&mise_dir).join("installs/java")),
+            None => dirs.push(home.join(".local/share/mise/installs/java")),
+        }
+    }
+    dirs
+}
+
+/// java home of the children of a directory, the bundles on macOS have it in Contents/Home
+fn list_homes(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            let bundle_home = entry.path().join("Contents/Home");
+            if bundle_home.is_dir() {
+                bundle_home
+            } else {
+                entry.path()
+            }
+        })
+        .collect()
+}
+
+/// java homes of the java executables in PATH, symlinks like /usr/bin/java are resolved
+fn path_homes() -> Vec<PathBuf> {
+    let Some(path) = std::env::var_os("PATH") else {
+        return Vec::new();
+    };
+    std::env::split_paths(&path)
+        .filter_map(|dir| canonicalize(dir.join(JAVA_EXECUTABLE)).ok())
+        .filter_map(|java| Some(java.parent()?.parent()?.to_path_buf()))
+        .collect()
+}
+
+/// find the java installations in JAVA_HOME, PATH, the common install directories,
+/// the version managers and the runtimes and JDKs installed by the launcher,
+/// the same java home found in several places is only returned once
+pub fn find_installations(runtimes_dir: &str) -> Vec<JavaInstallation> {
+    let mut homes = Vec::new();
+    if let Ok(entries) = read_dir(runtimes_dir) {
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let component = entry.file_name().to_string_lossy().to_string();
+            homes.push(PathBuf::from(get_runtime_home(runtimes_dir, &component)));
+        }
+    }
+    // the JDKs from Adoptium
+    homes.extend(list_homes(Path::new(runtimes_dir)));
+    if let Some(java_home) = std::env::var_os("JAVA_HOME") {
+        homes.push(PathBuf::from(java_home));
+    }
+    homes.extend(path_homes());
+    for dir in java_dirs() {
+        homes.extend(list_homes(&dir));
+    }
+
+    let mut real_paths = Vec::new();
+    let mut installations = Vec::new();
+    for home in homes {
+        if !home.join("bin").join(JAVA_EXECUTABLE).is_file() {
+            continue;
+        }
+        let Ok(real_path) = canonicalize(&home) else {
+            continue;
+        };
+        if real_paths.contains(&real_path) {
+            continue;
+        }
+        if let Ok(java) = JavaInstallation::new(real_path.to_string_lossy().to_string()) {
+            installations.push(java);
+        }
+        real_paths.push(real_path);
+    }
+    installations
+}
@@ -1,14 +1,16 @@
-use std::fs::{File, exists};
+use std::collections::HashMap;
+use std::fs::{File, canonicalize, exists};
 use std::io::{BufRead, BufReader};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-use super::java_version::JavaVersion;
+use super::java_version::{JavaVersion, JavaVersionError};
 
 pub enum JavaInstallationError {
     JavaExecutableNotFound,
     ReleaseFileInvalid,
     IOError(std::io::Error),
+    VersionError(JavaVersionError),
 }
 
 impl From<std::io::Error> for JavaInstallationError {
@@ -17,20 +19,204 @@ impl From<std::io::Error> for JavaInstallationError {
     }
 }
 
+impl From<JavaVersionError> for JavaInstallationError {
+    fn from(err: JavaVersionError) -> Self {
+        JavaInstallationError::VersionError(err)
+    }
+}
+
+/// a 32-bit JVM can hardly reserve a larger heap, especially on Windows
+const MAX_32_BIT_HEAP: u64 = 1536 * 1024 * 1024;
+
+/// modules the game needs, LWJGL and Netty use sun.misc.Unsafe in jdk.unsupported
+const REQUIRED_MODULES: [&str; 1] = ["jdk.unsupported"];
+
+/// the keys in the release file and the system properties with the same meaning,
+/// the properties are used when the release file is missing or incomplete
+const RELEASE_PROPERTIES: [(&str, &str); 4] = [
+    ("IMPLEMENTOR", "java.vendor"),
+    ("OS_ARCH", "os.arch"),
+    ("JAVA_RUNTIME_VERSION", "java.runtime.version"),
+    ("JVM_VARIANT", "java.vm.name"),
+];
+
+/// problems that may stop the game from running, found before launching
+pub enum JavaWarning {
+    /// the maximum heap size (MiB) is too large for a 32-bit JVM
+    LargeHeapOn32Bit(u64),
+    /// a module is missing, e.g. in a runtime created by jlink
+    MissingModule(String),
+    /// the java runs in emulation, with the architecture of the java and the host
+    Emulated(String, String),
+}
+
+impl std::fmt::Display for JavaWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JavaWarning::LargeHeapOn32Bit(xmx) => write!(
+                f,
+                "A 32-bit Java may fail to reserve {xmx} MiB of memory, please use a 64-bit Java."
+            ),
+            JavaWarning::MissingModule(module) => write!(
+                f,
+                "The Java does not have the module {module}, the game may crash."
+            ),
+            JavaWarning::Emulated(arch, host) => write!(
+                f,
+                "The Java is for {arch} and runs slowly in emulation on {host}."
+            ),
+        }
+    }
+}
+
 pub struct JavaInstallation {
     /// path of java home
     path: String,
     version: JavaVersion,
+    /// IMPLEMENTOR in the release file, e.g. Eclipse Adoptium
+    vendor: Option<String>,
+    /// OS_ARCH in the release file, e.g. x86_64, aarch64
+    arch: Option<String>,
+    /// JAVA_RUNTIME_VERSION in the release file, e.g. 21.0.1+12-LTS
+    runtime_version: Option<String>,
+    /// JVM_VARIANT in the release file or the name of the vm, e.g. Hotspot, Openj9
+    vm: Option<String>,
+    /// MODULES in the release file, None before Java 9 or if unknown
+    modules: Option<Vec<String>>,
 }
 
 impl JavaInstallation {
     pub fn new(path: String) -> Result<Self, JavaInstallationError> {
+        let mut release = Self::i_read_release(&path)?;
+        let release_version = release.get("JAVA_VERSION").and_then(|v| v.parse().ok());
+        let complete = release.contains_key("IMPLEMENTOR") && release.contains_key("OS_ARCH");
+        let version = match release_version {
+            Some(version) if complete => version,
+            // the release file is missing or incomplete, ask the java executable
+            release_version => {
+                match Self::i_get_java_path(&path).and_then(|java| Self::i_query_executable(&java))
+                {
+                    Ok((properties, version)) => {
+                        for (key, property) in RELEASE_PROPERTIES {
+                            if let Some(value) = properties.get(property) {
+                                release.entry(key.to_string()).or_insert(value.clone());
+                            }
+                        }
+                        release_version.unwrap_or(version)
+                    }
+                    // e.g. the java can not run on this machine
+                    Err(e) => release_version.ok_or(e)?,
+                }
+            }
+        };
         Ok(JavaInstallation {
-            path: path.clone(),
-            version: Self::i_get_version(&path)?,
+            path,
+            version,
+            vendor: release.get("IMPLEMENTOR").cloned(),
+            arch: release
+                .get("OS_ARCH")
+                .map(|arch| normalize_arch(arch).to_string()),
+            runtime_version: release.get("JAVA_RUNTIME_VERSION").cloned(),
+            vm: release.get("JVM_VARIANT").cloned(),
+            modules: release
+                .get("MODULES")
+                .map(|modules| modules.split_whitespace().map(String::from).collect()),
         })
     }
 
+    /// java_path is the java executable in the java home, symlinks like /usr/bin/java
+    /// are resolved, and a command like java is searched in PATH
+    pub fn from_executable(java_path: &str) -> Result<Self, JavaInstallationError> {
+        let mut path = PathBuf::from(java_path);
+        if path.components().count() == 1 {
+            let dirs = std::env::var_os("PATH").unwrap_or_default();
+            path = std::env::split_paths(&dirs)
+                .flat_map(|dir| [dir.join(java_path), dir.join(format!("{java_path}.exe"))])
+                .find(|path| path.is_file())
+                .ok_or(JavaInstallationError::JavaExecutableNotFound)?;
+        }
+        let java = canonicalize(path)?;
+        let home = java
+            .parent()
+            .and_then(|bin| bin.parent())
+            .ok_or(JavaInstallationError::JavaExecutableNotFound)?;
+        Self::new(home.to_string_lossy().to_string())
+    }
+
+    /// path of java home
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn get_version(&self) -> &JavaVersion {
+        &self.version
+    }
+
+    pub fn get_vendor(&self) -> Option<&str> {
+        self.vendor.as_deref()
+    }
+
+    /// x86_64, x86, aarch64, arm or others as is
+    pub fn get_arch(&self) -> Option<&str> {
+        self.arch.as_deref()
+    }
+
+    pub fn get_runtime_version(&self) -> Option<&str> {
+        self.runtime_version.as_deref()
+    }
+
+    pub fn get_vm(&self) -> Option<&str> {
+        self.vm.as_deref()
+    }
+
+    pub fn is_openj9(&self) -> bool {
+        self.vm
+            .as_ref()
+            .is_some_and(|vm| vm.to_lowercase().contains("openj9"))
+    }
+
+    /// None if the architecture is unknown
+    pub fn is_64_bit(&self) -> Option<bool> {
+        match self.arch.as_deref()? {
+            "x86" | "arm" | "ppc" => Some(false),
+            arch => Some(arch.contains("64") || arch == "s390x"),
+        }
+    }
+
+    /// None if the modules are unknown, e.g. before Java 9
+    pub fn has_module(&self, module: &str) -> Option<bool> {
+        self.modules
+            .as_ref()
+            .map(|modules| modules.iter().any(|m| m == module))
+    }
+
+    /// check the problems before launching, xmx is the maximum heap size in bytes
+    pub fn check(&self, xmx: Option<u64>) -> Vec<JavaWarning> {
+        let mut warnings = Vec::new();
+        match xmx {
+            Some(xmx) if self.is_64_bit() == Some(false) && xmx > MAX_32_BIT_HEAP => {
+                warnings.push(JavaWarning::LargeHeapOn32Bit(xmx / 1024 / 1024));
+            }
+            _ => {}
+        }
+        if self.version.get_feature() >= 9 {
+            for module in REQUIRED_MODULES {
+                if self.has_module(module) == Some(false) {
+                    warnings.push(JavaWarning::MissingModule(module.to_string()));
+                }
+            }
+        }
+        // Rosetta 2 on macOS and Prism on Windows
+        let host = normalize_arch(std::env::consts::ARCH);
+        match self.arch.as_deref() {
+            Some(arch @ ("x86_64" | "x86")) if host == "aarch64" => {
+                warnings.push(JavaWarning::Emulated(arch.to_string(), host.to_string()));
+            }
+            _ => {}
+        }
+        warnings
+    }
+
     pub fn get_java_path(&self) -> Result<String, JavaInstallationError> {
         Self::i_get_java_path(&self.path)
     }
@@ -46,8 +232,38 @@ impl JavaInstallation {
         }
     }
 
-    fn i_get_version(path: &str) -> Result<JavaVersion, JavaInstallationError> {
-        // try to read release file
+    /// get the version by running `java -version`, java_path can be a command in PATH
+    pub fn get_executable_version(java_path: &str) -> Result<JavaVersion, JavaInstallationError> {
+        // the output is in stderr
+        let output = Command::new(java_path).arg("-version").output()?.stderr;
+        // the output is in the system encoding, which may not be UTF-8
+        let output = String::from_utf8_lossy(&output);
+        Ok(JavaVersion::from_version_output(&output)?)
+    }
+
+    /// run `java -XshowSettings:properties -version`, get the system properties and the version
+    fn i_query_executable(
+        java_path: &str,
+    ) -> Result<(HashMap<String, String>, JavaVersion), JavaInstallationError> {
+        let output = Command::new(java_path)
+            .args(["-XshowSettings:properties", "-version"])
+            .output()?
+            .stderr;
+        let output = String::from_utf8_lossy(&output);
+
+        // like "    java.vendor = Eclipse Adoptium", the lines of multi-line values are skipped
+        let properties = output
+            .lines()
+            .filter_map(|line| line.strip_prefix("    ")?.split_once(" = "))
+            .filter(|(key, _)| !key.starts_with(' '))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        Ok((properties, JavaVersion::from_version_output(&output)?))
+    }
+
+    /// read the key-value pairs in the release file, empty if there is no release file
+    fn i_read_release(path: &str) -> Result<HashMap<String, String>, JavaInstallationError> {
+        let mut release = HashMap::new();
         let rel_path = format!("{}/release", &path);
         if exists(&rel_path)? {
             let file = File::open(Path::new(&rel_path))?;
@@ -55,32 +271,34 @@ impl JavaInstallation {
 
             for line in reader.lines() {
                 let line = line?;
-                if line.starts_with("JAVA_VERSION=") {
-                    let version_str = line.trim_start_matches("JAVA_VERSION=").trim_matches('"');
-                    return Ok(JavaVersion::from(version_str));
+                if let Some((key, value)) = line.split_once('=') {
+                    release.insert(key.to_string(), value.trim_matches('"').to_string());
                 }
             }
         }
+        Ok(release)
+    }
+}
 
-        // get the result of java -version
-        let java_path = Self::i_get_java_path(path)?;
-
-        // the output is in stderr
-        let output = Command::new(java_path).arg("-version").output()?.stderr;
-
-        let mut ver_vec = Vec::new();
-        let mut state = false;
-        for c in output {
-            if c == b'"' && state == false {
-                state = true;
-            } else if c == b'"' && state == true {
-                break;
-            } else if state == true {
-                ver_vec.push(c);
-            }
-        }
-
-        let ver_str = String::from_utf8(ver_vec).unwrap();
-        Ok(JavaVersion::from(ver_str.as_str()))
+/// the names differ between the release file, os.arch and rust, e.g. amd64 and x86_64
+fn normalize_arch(arch: &str) -> &str {
+    match arch {
+        "amd64" | "x86_64" | "x64" => "x86_64",
+        "x86" | "i386" | "i486" | "i586" | "i686" => "x86",
+        "aarch64" | "arm64" => "aarch64",
+        "arm" | "aarch32" => "arm",
+        arch => arch,
     }
 }
+
+/// select an installation for the required major version,
+/// the same major version is preferred, then the nearest newer one
+pub fn find_compatible<'a>(
+    installations: impl IntoIterator<Item = &'a JavaInstallation>,
+    feature: u8,
+) -> Option<&'a JavaInstallation> {
+    installations
+        .into_iter()
+        .filter(|java| java.version.get_feature() >= feature)
+        .min_by_key(|java| (java.version.get_feature(), std::cmp::Reverse(&java.version)))
+}
@@ -1,58 +1,179 @@
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+/// a version in the JEP 223 format, or the legacy format before Java 9,
+/// e.g. "21.0.1+12-LTS", "22-ea", "1.8.0_391-b13"
+#[derive(Debug, PartialEq, Eq)]
 pub struct JavaVersion {
     feature: u8,
     interim: u8,
     update: u32,
     patch: u32,
+    /// pre-release identifier, e.g. ea
+    pre: Option<String>,
+    build: Option<u32>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum JavaVersionError {
+    Empty,
+    /// a field of the version number is not a number
+    InvalidNumber(String),
+    /// no version is found in the output of `java -version`
+    NotFound,
 }
 
-impl From<&str> for JavaVersion {
-    fn from(version_str: &str) -> Self {
-        let vec = version_str.split('.').collect::<Vec<&str>>();
-        if version_str.starts_with("1.") {
-            // <= Java 8
-            let vec2 = vec[2].split("_").collect::<Vec<&str>>();
-            JavaVersion {
-                feature: vec[1].parse().unwrap_or(0),
-                interim: vec2[0].parse().unwrap_or(0),
-                update: vec2[1].parse().unwrap_or(0),
+impl std::fmt::Display for JavaVersionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JavaVersionError::Empty => write!(f, "Java version is empty"),
+            JavaVersionError::InvalidNumber(s) => write!(f, "{s} is not a valid version number"),
+            JavaVersionError::NotFound => write!(f, "No Java version is found"),
+        }
+    }
+}
+
+fn parse_number<T: FromStr>(field: &str) -> Result<T, JavaVersionError> {
+    field
+        .parse()
+        .map_err(|_| JavaVersionError::InvalidNumber(field.to_string()))
+}
+
+impl FromStr for JavaVersion {
+    type Err = JavaVersionError;
+
+    fn from_str(version_str: &str) -> Result<Self, Self::Err> {
+        let version_str = version_str.trim();
+        if version_str.is_empty() {
+            return Err(JavaVersionError::Empty);
+        }
+        // $VNUM(-$PRE)?(\+$BUILD)?(-$OPT)?
+        let (vnum, suffix) = match version_str.find(['-', '+']) {
+            Some(i) => version_str.split_at(i),
+            None => (version_str, ""),
+        };
+        let (pre, build) = match suffix.strip_prefix('-') {
+            Some(rest) => match rest.split_once('+') {
+                Some((pre, build)) => (Some(pre), Some(build)),
+                None => (Some(rest), None),
+            },
+            None => (None, suffix.strip_prefix('+')),
+        };
+        // $OPT after the build number is ignored
+        let build = build.map(|build| build.split('-').next().unwrap_or_default());
+        let build = build
+            .filter(|build| !build.is_empty())
+            .map(parse_number)
+            .transpose()?;
+        let pre = pre.map(|pre| pre.split('-').next().unwrap_or_default());
+
+        // missing fields are treated as 0, e.g. "21"
+        let vec = vnum.split('.').collect::<Vec<&str>>();
+        let field = |i: usize| vec.get(i).copied().unwrap_or("0");
+        if vec.len() > 1 && vec[0] == "1" {
+            // <= Java 8, e.g. 1.8.0_391-b13, where b13 is the build number
+            let (interim, update) = field(2).split_once('_').unwrap_or((field(2), "0"));
+            let (pre, build) = match pre.and_then(|pre| pre.strip_prefix('b')) {
+                Some(build) if build.parse::<u32>().is_ok() => (None, Some(parse_number(build)?)),
+                _ => (pre, build),
+            };
+            Ok(JavaVersion {
+                feature: parse_number(field(1))?,
+                interim: parse_number(interim)?,
+                update: parse_number(update)?,
                 patch: 0,
-            }
+                pre: pre.map(|pre| pre.to_string()),
+                build,
+            })
         } else {
             // >= Java 9
-            let mut patch = 0;
-            if vec.len() == 4 {
-                patch = vec[3].parse().unwrap_or(0);
-            }
-            JavaVersion {
-                feature: vec[0].parse().unwrap_or(0),
-                interim: vec[1].parse().unwrap_or(0),
-                update: vec[2].parse().unwrap_or(0),
-                patch,
-            }
+            Ok(JavaVersion {
+                feature: parse_number(field(0))?,
+                interim: parse_number(field(1))?,
+                update: parse_number(field(2))?,
+                patch: parse_number(field(3))?,
+                pre: pre.map(|pre| pre.to_string()),
+                build,
+            })
         }
     }
 }
 
+impl TryFrom<&str> for JavaVersion {
+    type Error = JavaVersionError;
+
+    fn try_from(version_str: &str) -> Result<Self, Self::Error> {
+        version_str.parse()
+    }
+}
+
+impl Ord for JavaVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.feature, self.interim, self.update, self.patch)
+            .cmp(&(other.feature, other.interim, other.update, other.patch))
+            // a pre-release is older than the release
+            .then_with(|| match (&self.pre, &other.pre) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(a), Some(b)) => a.cmp(b),
+            })
+            .then_with(|| self.build.cmp(&other.build))
+    }
+}
+
+impl PartialOrd for JavaVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl std::fmt::Display for JavaVersion {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if self.feature <= 8 {
-            write!(f, "1.{}.{}.{}", self.feature, self.interim, self.update)
+            write!(f, "1.{}.{}.{}", self.feature, self.interim, self.update)?;
+        } else if self.patch == 0 {
+            write!(f, "{}.{}.{}", self.feature, self.interim, self.update)?;
         } else {
-            if self.patch == 0 {
-                write!(f, "{}.{}.{}", self.feature, self.interim, self.update)
-            } else {
-                write!(
-                    f,
-                    "{}.{}.{}.{}",
-                    self.feature, self.interim, self.update, self.patch
-                )
-            }
+            write!(
+                f,
+                "{}.{}.{}.{}",
+                self.feature, self.interim, self.update, self.patch
+            )?;
         }
+        if let Some(pre) = &self.pre {
+            write!(f, "-{pre}")?;
+        }
+        Ok(())
     }
 }
 
 impl JavaVersion {
+    /// parse the output of `java -version`, the version is quoted in the first line like
+    /// `openjdk version "21.0.1" 2023-10-17`, lines like `Picked up JAVA_TOOL_OPTIONS` are skipped
+    pub fn from_version_output(output: &str) -> Result<Self, JavaVersionError> {
+        let line = output
+            .lines()
+            .find(|line| line.contains(" version \""))
+            .ok_or(JavaVersionError::NotFound)?;
+        let quoted = line.split('"').nth(1).ok_or(JavaVersionError::NotFound)?;
+        quoted.parse()
+    }
+
+    /// the major version, e.g. 8, 17, 21
+    pub fn get_feature(&self) -> u8 {
+        self.feature
+    }
+
+    /// pre-release identifier, e.g. ea
+    pub fn get_pre(&self) -> Option<&str> {
+        self.pre.as_deref()
+    }
+
+    pub fn get_build(&self) -> Option<u32> {
+        self.build
+    }
+
     pub fn check_minimum_version(&self, min_version: &JavaVersion) -> bool {
         self >= min_version
     }
@@ -61,3 +182,175 @@ impl JavaVersion {
         self <= max_version
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(feature: u8, interim: u8, update: u32, patch: u32) -> JavaVersion {
+        JavaVersion {
+            feature,
+            interim,
+            update,
+            patch,
+            pre: None,
+            build: None,
+        }
+    }
+
+    #[test]
+    fn parse_short_versions() {
+        assert_eq!("17".parse(), Ok(version(17, 0, 0, 0)));
+        assert_eq!("21.0.1".parse(), Ok(version(21, 0, 1, 0)));
+        assert_eq!("17.0.8.1".parse(), Ok(version(17, 0, 8, 1)));
+        assert_eq!(JavaVersion::try_from("1.8.0"), Ok(version(8, 0, 0, 0)));
+        assert_eq!(JavaVersion::try_from("1.8"), Ok(version(8, 0, 0, 0)));
+    }
+
+    #[test]
+    fn parse_jep_223() {
+        let ea: JavaVersion = "21-ea".parse().unwrap();
+        assert_eq!(ea.get_feature(), 21);
+        assert_eq!(ea.get_pre(), Some("ea"));
+        assert_eq!(ea.get_build(), None);
+
+        let build: JavaVersion = "22+36".parse().unwrap();
+        assert_eq!(build.get_feature(), 22);
+        assert_eq!(build.get_pre(), None);
+        assert_eq!(build.get_build(), Some(36));
+
+        let lts: JavaVersion = "21.0.1+12-LTS".parse().unwrap();
+        assert_eq!(lts.to_string(), "21.0.1");
+        assert_eq!(lts.get_build(), Some(12));
+
+        let ea_build: JavaVersion = "23-ea+5-123".parse().unwrap();
+        assert_eq!(ea_build.get_pre(), Some("ea"));
+        assert_eq!(ea_build.get_build(), Some(5));
+    }
+
+    #[test]
+    fn parse_legacy() {
+        let java8: JavaVersion = "1.8.0_391-b13".parse().unwrap();
+        assert_eq!(java8.get_feature(), 8);
+        assert_eq!(java8.get_pre(), None);
+        assert_eq!(java8.get_build(), Some(13));
+        assert_eq!(java8.to_string(), "1.8.0.391");
+
+        let ea: JavaVersion = "1.8.0-ea".parse().unwrap();
+        assert_eq!(ea.get_pre(), Some("ea"));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert_eq!("".parse::<JavaVersion>(), Err(JavaVersionError::Empty));
+        assert!(matches!(
+            "zulu".parse::<JavaVersion>(),
+            Err(JavaVersionError::InvalidNumber(_))
+        ));
+        assert!(matches!(
+            "17.0.x".parse::<JavaVersion>(),
+            Err(JavaVersionError::InvalidNumber(_))
+        ));
+        assert!(matches!(
+            "1000".parse::<JavaVersion>(),
+            Err(JavaVersionError::InvalidNumber(_))
+        ));
+        assert!(matches!(
+            "22+b".parse::<JavaVersion>(),
+            Err(JavaVersionError::InvalidNumber(_))
+        ));
+    }
+
+    #[test]
+    fn compare() {
+        let parse = |s: &str| s.parse::<JavaVersion>().unwrap();
+        assert!(parse("21-ea") < parse("21"));
+        assert!(parse("21") < parse("21.0.1"));
+        assert!(parse("22+35") < parse("22+36"));
+        assert!(parse("1.8.0_391") < parse("11"));
+        assert!(parse("17.0.8") < parse("17.0.8.1"));
+    }
+
+    #[test]
+    fn output_temurin() {
+        let output = "openjdk version \"21.0.1\" 2023-10-17 LTS
+OpenJDK Runtime Environment Temurin-21.0.1+12 (build 21.0.1+12-LTS)
+OpenJDK 64-Bit Server VM Temurin-21.0.1+12 (build 21.0.1+12-LTS, mixed mode, sharing)
+";
+        assert_eq!(
+            JavaVersion::from_version_output(output),
+            Ok(version(21, 0, 1, 0))
+        );
+    }
+
+    #[test]
+    fn output_zulu() {
+        let output = "openjdk version \"17.0.9\" 2023-10-17 LTS
+OpenJDK Runtime Environment Zulu17.46+19-CA (build 17.0.9+8-LTS)
+OpenJDK 64-Bit Server VM Zulu17.46+19-CA (build 17.0.9+8-LTS, mixed mode, sharing)
+";
+        assert_eq!(
+            JavaVersion::from_version_output(output),
+            Ok(version(17, 0, 9, 0))
+        );
+    }
+
+    #[test]
+    fn output_graalvm() {
+        let output = "java version \"21.0.1\" 2023-10-17
+Java(TM) SE Runtime Environment Oracle GraalVM 21.0.1+12.1 (build 21.0.1+12-jvmci-23.1-b19)
+Java HotSpot(TM) 64-Bit Server VM Oracle GraalVM 21.0.1+12.1 (build 21.0.1+12-jvmci-23.1-b19, mixed mode, sharing)
+";
+        assert_eq!(
+            JavaVersion::from_version_output(output),
+            Ok(version(21, 0, 1, 0))
+        );
+    }
+
+    #[test]
+    fn output_openj9() {
+        let output = "openjdk version \"17.0.8.1\" 2023-08-24
+IBM Semeru Runtime Open Edition 17.0.8.1 (build 17.0.8.1+1)
+Eclipse OpenJ9 VM 17.0.8.1 (build openj9-0.40.0, JRE 17 Linux amd64-64-Bit Compressed References 20230824_560 (JIT enabled, AOT enabled)
+OpenJ9   - d12d10c9e
+OMR      - 1d569b3a1
+JCL      - 3ff1a7f9b1 based on jdk-17.0.8.1+1)
+";
+        assert_eq!(
+            JavaVersion::from_version_output(output),
+            Ok(version(17, 0, 8, 1))
+        );
+    }
+
+    #[test]
+    fn output_oracle_8() {
+        let output = "java version \"1.8.0_391\"
+Java(TM) SE Runtime Environment (build 1.8.0_391-b13)
+Java HotSpot(TM) 64-Bit Server VM (build 25.391-b13, mixed mode)
+";
+        assert_eq!(
+            JavaVersion::from_version_output(output),
+            Ok(version(8, 0, 391, 0))
+        );
+    }
+
+    #[test]
+    fn output_tool_options() {
+        let output = "Picked up JAVA_TOOL_OPTIONS: -Dfile.encoding=\"UTF-8\"
+openjdk version \"22-ea\" 2024-03-19
+OpenJDK Runtime Environment (build 22-ea+27-2262)
+OpenJDK 64-Bit Server VM (build 22-ea+27-2262, mixed mode, sharing)
+";
+        let java = JavaVersion::from_version_output(output).unwrap();
+        assert_eq!(java.get_feature(), 22);
+        assert_eq!(java.get_pre(), Some("ea"));
+    }
+
+    #[test]
+    fn output_not_found() {
+        assert_eq!(
+            JavaVersion::from_version_output("Error: could not find libjava.so"),
+            Err(JavaVersionError::NotFound)
+        );
+    }
+}
@@ -1,2 +1,5 @@
+pub mod adoptium;
+pub mod discovery;
 pub mod java;
 pub mod java_version;
+pub mod runtime;
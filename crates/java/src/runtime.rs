@@ -0,0 +1,195 @@
+//! Mojang's java runtimes, listed in the java-runtime manifest
+
+use log::{error, info};
+use serde_json::Value;
+use std::fs::{create_dir_all, exists, remove_file};
+
+/// all the runtimes for all the platforms, the same as the official launcher
+const RUNTIME_MANIFEST: &str = "https://launchermeta.mojang.com/v1/products/java-runtime/2ec0cc96c44e5a76b9c8b7c39df7210883d12871/all.json";
+
+pub enum RuntimeError {
+    DataInvalid,
+    /// the component is not provided for this platform
+    NotAvailable(String),
+    DeserializeError(serde_json::Error),
+    IOError(std::io::Error),
+    ReqwestError(reqwest::Error),
+}
+
+impl From<serde_json::Error> for RuntimeError {
+    fn from(err: serde_json::Error) -> Self {
+        RuntimeError::DeserializeError(err)
+    }
+}
+
+impl From<std::io::Error> for RuntimeError {
+    fn from(err: std::io::Error) -> Self {
+        RuntimeError::IOError(err)
+    }
+}
+
+impl From<reqwest::Error> for RuntimeError {
+    fn from(err: reqwest::Error) -> Self {
+        RuntimeError::ReqwestError(err)
+    }
+}
+
+impl std::fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuntimeError::DataInvalid => write!(f, "Runtime manifest is invalid"),
+            RuntimeError::NotAvailable(s) => write!(f, "{s} is not available for this platform"),
+            RuntimeError::DeserializeError(err) => write!(f, "{err}"),
+            RuntimeError::IOError(err) => write!(f, "{err}"),
+            RuntimeError::ReqwestError(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+/// a file of the runtime, on_finish checks the sha1 and sets the executable bit
+pub struct RuntimeFile {
+    pub url: String,
+    pub save_path: String,
+    pub on_finish: Box<dyn Fn() + Send + Sync>,
+}
+
+/// the platform name in the manifest, None if Mojang provides no runtime for it
+pub fn get_platform() -> Option<&'static str> {
+    match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("linux", "x86_64") => Some("linux"),
+        ("linux", "x86") => Some("linux-i386"),
+        ("macos", "x86_64") => Some("mac-os"),
+        ("macos", "aarch64") => Some("mac-os-arm64"),
+        ("windows", "x86_64") => Some("windows-x64"),
+        ("windows", "x86") => Some("windows-x86"),
+        ("windows", "aarch64") => Some("windows-arm64"),
+        _ => None,
+    }
+}
+
+/// java home of the component, runtimes for macOS are bundles
+pub fn get_runtime_home(runtimes_dir: &str, component: &str) -> String {
+    let dir = format!("{runtimes_dir}/{component}");
+    if cfg!(target_os = "macos") {
+        dir + "/jre.bundle/Contents/Home"
+    } else {
+        dir
+    }
+}
+
+#[cfg(unix)]
+fn set_executable(path: &str) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+}
+
+#[cfg(not(unix))]
+fn set_executable(_path: &str) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn create_link(path: &str, target: &str) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, path)
+}
+
+/// links only exist in the runtimes for unix
+#[cfg(not(unix))]
+fn create_link(_path: &str, _target: &str) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// called after a file is downloaded, a corrupted file is removed
+fn finish_file(path: &str, sha1: &str, executable: bool) {
+    match utils::check_sha1(path, sha1) {
+        Ok(true) if executable => {
+            if let Err(e) = set_executable(path) {
+                error!("Failed to set {path} executable. Reason: {e}");
+            }
+        }
+        Ok(true) => {}
+        Ok(false) => {
+            error!("The sha1 of {path} does not match, it is removed.");
+            if let Err(e) = remove_file(path) {
+                error!("{e}");
+            }
+        }
+        Err(e) => error!("Failed to check {path}. Reason: {e}"),
+    }
+}
+
+/// create the directories and links of the component, and get the files to download,
+/// the files already downloaded are skipped
+pub async fn install_runtime(
+    runtimes_dir: &str,
+    component: &str,
+) -> Result<Vec<RuntimeFile>, RuntimeError> {
+    let not_available = || RuntimeError::NotAvailable(component.to_string());
+    let platform = get_platform().ok_or_else(not_available)?;
+    let text = reqwest::get(RUNTIME_MANIFEST).await?.text().await?;
+    let json = serde_json::from_str::<Value>(&text)?;
+    let node = &json[platform][component][0];
+    let url = node["manifest"]["url"].as_str().ok_or_else(not_available)?;
+    info!(
+        "Install {component} {} for {platform}.",
+        node["version"]["name"].as_str().unwrap_or_default()
+    );
+
+    let text = reqwest::get(url).await?.text().await?;
+    let manifest = serde_json::from_str::<Value>(&text)?;
+    let dir = format!("{runtimes_dir}/{component}");
+    let mut files = Vec::new();
+    let mut links = Vec::new();
+    for (path, node) in manifest["files"]
+        .as_object()
+        .ok_or(RuntimeError::DataInvalid)?
+    {
+        let save_path = format!("{dir}/{path}");
+        match node["type"].as_str() {
+            Some("directory") => create_dir_all(&save_path)?,
+            Some("file") => {
+                let raw = &node["downloads"]["raw"];
+                let sha1 = raw["sha1"]
+                    .as_str()
+                    .ok_or(RuntimeError::DataInvalid)?
+                    .to_string();
+                let executable = node["executable"].as_bool().unwrap_or(false);
+                if exists(&save_path)? {
+                    if utils::check_sha1(&save_path, &sha1)? {
+                        if executable {
+                            set_executable(&save_path)?;
+                        }
+                        continue;
+                    }
+                    // the downloader appends to an existing file
+                    remove_file(&save_path)?;
+                }
+                create_dir_all(utils::get_parent_dir(&save_path))?;
+
+                let url = raw["url"]
+                    .as_str()
+                    .ok_or(RuntimeError::DataInvalid)?
+                    .to_string();
+                let finish_path = save_path.clone();
+                files.push(RuntimeFile {
+                    url,
+                    save_path,
+                    on_finish: Box::new(move || finish_file(&finish_path, &sha1, executable)),
+                });
+            }
+            Some("link") => {
+                let target = node["target"].as_str().ok_or(RuntimeError::DataInvalid)?;
+                links.push((save_path, target.to_string()));
+            }
+            _ => return Err(RuntimeError::DataInvalid),
+        }
+    }
+
+    // the parent directories of the links are created above
+    for (path, target) in links {
+        if std::fs::symlink_metadata(&path).is_err() {
+            create_link(&path, &target)?;
+        }
+    }
+    Ok(files)
+}
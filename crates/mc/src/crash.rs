@@ -0,0 +1,332 @@
+//! 游戏崩溃分析
+//! mc::crash 查找崩溃报告并识别常见原因
+
+use std::fs::{self, exists};
+use std::time::SystemTime;
+
+/// 显卡驱动的库名
+const DRIVER_LIBS: [&str; 12] = [
+    "atio6axx",
+    "atioglxx",
+    "nvoglv",
+    "libnvidia",
+    "ig4icd",
+    "ig7icd",
+    "ig9icd",
+    "ig75icd",
+    "igxelpicd",
+    "radeonsi",
+    "iris_dri",
+    "i965_dri",
+];
+
+/// 崩溃原因
+pub enum CrashCause {
+    /// Java版本过低，需要的Java主版本
+    WrongJava(Option<u32>),
+    /// 内存不足
+    OutOfMemory,
+    /// 缺少前置mod，相关的日志行
+    MissingDependency(String),
+    /// mod重复，相关的日志行
+    DuplicateMods(String),
+    /// Mixin注入失败，相关的日志行
+    Mixin(String),
+    /// 显卡驱动崩溃
+    GraphicsDriver,
+    /// 未能识别
+    Unknown,
+}
+
+/// 崩溃分析结果
+pub struct CrashReport {
+    pub cause: CrashCause,
+    /// 退出码，被信号终止时为None
+    pub exit_code: Option<i32>,
+    /// 找到的崩溃报告及JVM错误日志
+    pub files: Vec<String>,
+}
+
+impl std::fmt::Display for CrashCause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CrashCause::WrongJava(Some(version)) => write!(
+                f,
+                "This version requires Java {version} or later. Please choose another Java in the game settings."
+            ),
+            CrashCause::WrongJava(None) => write!(
+                f,
+                "The Java version does not match this version. Please choose another Java in the game settings."
+            ),
+            CrashCause::OutOfMemory => write!(
+                f,
+                "The game ran out of memory. Please increase Xmx in the game settings, or close other programs."
+            ),
+            CrashCause::MissingDependency(line) => write!(
+                f,
+                "A mod is missing its dependency. Please install the required mod.\n{line}"
+            ),
+            CrashCause::DuplicateMods(line) => write!(
+                f,
+                "Duplicate mods were found. Please remove the extra copies from the mods folder.\n{line}"
+            ),
+            CrashCause::Mixin(line) => write!(
+                f,
+                "A mod failed to apply its Mixin. Please update or remove the mod.\n{line}"
+            ),
+            CrashCause::GraphicsDriver => write!(
+                f,
+                "The graphics driver crashed. Please update your graphics driver."
+            ),
+            CrashCause::Unknown => write!(f, "Unknown cause."),
+        }
+    }
+}
+
+impl std::fmt::Display for CrashReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.exit_code {
+            Some(code) => writeln!(f, "Exit code: {code}")?,
+            None => writeln!(f, "Terminated by a signal")?,
+        }
+        write!(f, "{}", self.cause)?;
+        for file in &self.files {
+            write!(f, "\n{file}")?;
+        }
+        Ok(())
+    }
+}
+
+/// 列出dir中修改时间晚于since的文件，按修改时间从新到旧排序
+fn list_newer(dir: &str, since: SystemTime, filter: impl Fn(&str) -> bool) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<(SystemTime, String)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| filter(&entry.file_name().to_string_lossy()))
+        .filter_map(|entry| {
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            if modified < since {
+                return None;
+            }
+            Some((modified, entry.path().to_string_lossy().to_string()))
+        })
+        .collect();
+    files.sort_by_key(|file| std::cmp::Reverse(file.0));
+    files.into_iter().map(|(_, path)| path).collect()
+}
+
+/// 查找本次运行产生的崩溃报告和JVM错误日志
+/// JVM错误日志写入工作目录，所以也需要查找启动器目录
+fn find_crash_files(game_dir: &str, since: SystemTime) -> Vec<String> {
+    let mut files = Vec::new();
+    let crash_dir = game_dir.to_string() + "/crash-reports";
+    if let Ok(true) = exists(&crash_dir) {
+        files.extend(
+            list_newer(&crash_dir, since, |name| name.ends_with(".txt"))
+                .into_iter()
+                .take(1),
+        );
+    }
+
+    let is_hs_err = |name: &str| name.starts_with("hs_err_pid") && name.ends_with(".log");
+    for dir in [game_dir, "."] {
+        files.extend(list_newer(dir, since, is_hs_err).into_iter().take(1));
+    }
+    files
+}
+
+/// 从UnsupportedClassVersionError中获取需要的Java版本
+fn required_java(line: &str) -> Option<u32> {
+    // "class file version 65.0" 或 "Unsupported class file major version 65"
+    let rest = if let Some(index) = line.find("class file version ") {
+        &line[index + "class file version ".len()..]
+    } else if let Some(index) = line.find("class file major version ") {
+        &line[index + "class file major version ".len()..]
+    } else {
+        return None;
+    };
+    let major: u32 = rest
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>()
+        .parse()
+        .ok()?;
+    // class file 52 对应 Java 8
+    major.checked_sub(44)
+}
+
+/// 查找第一个包含任一关键字的行
+fn find_line<'a>(lines: &[&'a str], keywords: &[&str]) -> Option<&'a str> {
+    lines
+        .iter()
+        .find(|line| keywords.iter().any(|k| line.contains(k)))
+        .map(|line| line.trim())
+}
+
+/// 根据日志内容识别崩溃原因
+fn match_cause(text: &str) -> CrashCause {
+    let lines: Vec<&str> = text.lines().collect();
+
+    if let Some(line) = find_line(
+        &lines,
+        &[
+            "UnsupportedClassVersionError",
+            "Unsupported class file major version",
+        ],
+    ) {
+        return CrashCause::WrongJava(required_java(line));
+    }
+
+    if find_line(
+        &lines,
+        &[
+            "java.lang.OutOfMemoryError",
+            "Could not reserve enough space",
+            "There is insufficient memory for the Java Runtime Environment",
+        ],
+    )
+    .is_some()
+    {
+        return CrashCause::OutOfMemory;
+    }
+
+    if let Some(line) = find_line(
+        &lines,
+        &[
+            "which is missing!",
+            "Missing or unsupported mandatory dependencies",
+            "MissingModsException",
+        ],
+    ) {
+        return CrashCause::MissingDependency(line.to_string());
+    }
+
+    if let Some(line) = find_line(
+        &lines,
+        &[
+            "DuplicateModsFoundException",
+            "Found duplicate mods",
+            "Duplicate mods found",
+            "is provided by multiple mods",
+        ],
+    ) {
+        return CrashCause::DuplicateMods(line.to_string());
+    }
+
+    if let Some(line) = find_line(
+        &lines,
+        &[
+            "MixinApplyError",
+            "MixinTransformerError",
+            "InvalidMixinException",
+            "Mixin apply failed",
+            "Mixin apply for mod",
+        ],
+    ) {
+        return CrashCause::Mixin(line.to_string());
+    }
+
+    // JVM错误日志中，"Problematic frame:"的下一行为崩溃位置
+    let in_driver = lines
+        .iter()
+        .position(|line| line.contains("Problematic frame"))
+        .and_then(|index| lines.get(index + 1))
+        .is_some_and(|frame| DRIVER_LIBS.iter().any(|lib| frame.contains(lib)));
+    if in_driver
+        || find_line(
+            &lines,
+            &[
+                "Pixel format not accelerated",
+                "GLFW error 65542",
+                "GLFW error 65543",
+                "Failed to create OpenGL context",
+            ],
+        )
+        .is_some()
+    {
+        return CrashCause::GraphicsDriver;
+    }
+
+    CrashCause::Unknown
+}
+
+/// 分析游戏崩溃原因
+/// game_dir: 游戏目录，start_time: 游戏启动时间，output: 游戏最近的输出
+pub fn analyze(
+    game_dir: &str,
+    start_time: SystemTime,
+    exit_code: Option<i32>,
+    output: &[String],
+) -> CrashReport {
+    let files = find_crash_files(game_dir, start_time);
+
+    let mut text = String::new();
+    for file in &files {
+        if let Ok(content) = fs::read(file) {
+            text += &String::from_utf8_lossy(&content);
+            text += "\n";
+        }
+    }
+    text += &output.join("\n");
+
+    CrashReport {
+        cause: match_cause(&text),
+        exit_code,
+        files,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn required_java_from_class_version() {
+        let line = "java.lang.UnsupportedClassVersionError: net/minecraft/client/main/Main has been compiled by a more recent version of the Java Runtime (class file version 65.0), this version of the Java Runtime only recognizes class file versions up to 52.0";
+        assert_eq!(required_java(line), Some(21));
+        assert_eq!(
+            required_java("Unsupported class file major version 61"),
+            Some(17)
+        );
+        assert_eq!(required_java("class file version 52.0"), Some(8));
+        assert_eq!(required_java("class file version 40.0"), None);
+        assert_eq!(required_java("UnsupportedClassVersionError"), None);
+    }
+
+    #[test]
+    fn match_cause_by_keywords() {
+        let text = "[main/INFO]: Loading\nException in thread \"main\" java.lang.UnsupportedClassVersionError: Main (class file version 61.0)\njava.lang.OutOfMemoryError: Java heap space";
+        assert!(matches!(match_cause(text), CrashCause::WrongJava(Some(17))));
+        assert!(matches!(
+            match_cause("java.lang.OutOfMemoryError: Java heap space"),
+            CrashCause::OutOfMemory
+        ));
+        assert!(matches!(
+            match_cause("  Mod 'Sodium Extra' (sodium-extra) requires mod sodium, which is missing!"),
+            CrashCause::MissingDependency(line) if line.starts_with("Mod 'Sodium Extra'")
+        ));
+        assert!(matches!(
+            match_cause("net.fabricmc.loader.impl.FormattedException: Found duplicate mods"),
+            CrashCause::DuplicateMods(_)
+        ));
+        assert!(matches!(
+            match_cause("org.spongepowered.asm.mixin.transformer.throwables.MixinTransformerError"),
+            CrashCause::Mixin(_)
+        ));
+        assert!(matches!(
+            match_cause("# Problematic frame:\n# C  [atio6axx.dll+0x1a2b3c]"),
+            CrashCause::GraphicsDriver
+        ));
+        assert!(matches!(
+            match_cause("GLFW error 65542: WGL: The driver does not appear to support OpenGL"),
+            CrashCause::GraphicsDriver
+        ));
+        assert!(matches!(
+            match_cause("# Problematic frame:\n# V  [libjvm.so+0x123456]"),
+            CrashCause::Unknown
+        ));
+    }
+}
@@ -1,37 +1,92 @@
 //! Download assets
 
+use log::error;
 use serde_json::Value;
-use std::fs::{create_dir_all, exists, read_to_string};
+use std::fs::{copy, create_dir_all, exists, metadata, read_to_string};
+
+use utils::get_parent_dir;
 
 use super::{DownloadError, DownloadTask};
 
-/// 下载assets
+/// 旧版本assets需要复制到的位置，新版本返回None
+/// map_to_resources: <game_dir>/resources (pre-1.6)
+/// virtual: assets/virtual/<id> (legacy)
+fn get_legacy_dir(json: &Value, path: &str, id: &str, game_dir: &str) -> Option<String> {
+    if json["map_to_resources"].as_bool().unwrap_or(false) {
+        Some(game_dir.to_string() + "/resources")
+    } else if json["virtual"].as_bool().unwrap_or(false) {
+        Some(path.to_string() + "/assets/virtual/" + id)
+    } else {
+        None
+    }
+}
+
+/// 获取${game_assets}，需要先下载asset index
+pub fn get_game_assets(path: &str, id: &str, game_dir: &str) -> Result<String, DownloadError> {
+    let index_path = path.to_string() + "/assets/indexes/" + id + ".json";
+    let json = serde_json::from_str::<Value>(&read_to_string(&index_path)?)?;
+    Ok(get_legacy_dir(&json, path, id, game_dir).unwrap_or(path.to_string() + "/assets"))
+}
+
+/// 将objects中的文件复制到旧版本的位置
+fn copy_asset(obj_path: &str, target_path: &str, size: u64) -> Result<(), DownloadError> {
+    if exists(target_path)? && metadata(target_path)?.len() == size {
+        return Ok(());
+    }
+
+    let dir = get_parent_dir(target_path);
+    if !exists(&dir)? {
+        create_dir_all(&dir)?;
+    }
+    copy(obj_path, target_path)?;
+    Ok(())
+}
+
+/// 下载assets，game_dir: 版本隔离后的游戏目录
 pub fn download_assets(
     path: &str,
     id: &str,
     mirror: &str,
+    game_dir: &str,
 ) -> Result<Vec<DownloadTask>, DownloadError> {
     let assets_dir = path.to_string() + "/assets";
     let index_path = assets_dir.clone() + "/indexes/" + &id + ".json";
     let json = serde_json::from_str::<Value>(&read_to_string(&index_path)?)?;
+    let legacy_dir = get_legacy_dir(&json, path, id, game_dir);
     let mut tasks = Vec::new();
-    for (_, node) in json["objects"]
+    for (name, node) in json["objects"]
         .as_object()
         .ok_or(DownloadError::DataInvalid)?
     {
         let hash = node["hash"].as_str().ok_or(DownloadError::DataInvalid)?;
+        let size = node["size"].as_u64().ok_or(DownloadError::DataInvalid)?;
         let dl_path = hash[0..2].to_string() + "/" + hash;
         let obj_path = assets_dir.clone() + "/objects";
         let save_path = obj_path.clone() + "/" + &dl_path;
+        let target_path = legacy_dir.as_ref().map(|dir| dir.clone() + "/" + name);
         if !exists(&save_path)? {
             let dir = obj_path.clone() + "/" + &hash[0..2];
             if !exists(&dir)? {
                 create_dir_all(&dir)?;
             }
             let url = mirror.to_string() + "/" + &dl_path;
-            tasks.push(DownloadTask::new(url, save_path, None));
+            let on_finish: Option<Box<dyn Fn() + Send + Sync>> = match target_path {
+                Some(target_path) => {
+                    let save_path = save_path.clone();
+                    Some(Box::new(move || {
+                        if copy_asset(&save_path, &target_path, size).is_err() {
+                            error!("Failed to copy {save_path} to {target_path}.");
+                        }
+                    }))
+                }
+                None => None,
+            };
+            tasks.push(DownloadTask::new(url, save_path, on_finish));
         } else {
             // TODO: check hash
+            if let Some(target_path) = target_path {
+                copy_asset(&save_path, &target_path, size)?;
+            }
         }
     }
 
@@ -1,13 +1,42 @@
 //! Download libraries
 
-use serde_json::Value;
+use log::{error, info};
+use serde_json::{Value, json};
 use std::env::consts as env;
-use std::fs::{copy, create_dir_all, exists, remove_dir_all};
+use std::fs::{
+    File, create_dir_all, exists, read_dir, read_to_string, remove_dir_all, rename, write,
+};
+use std::io;
+use std::sync::Mutex;
 
-use utils::{check_rules, get_parent_dir, list_file};
+use utils::{check_rules, get_parent_dir, get_sha1, get_str_sha1};
 
 use super::{DownloadError, DownloadTask, TaskInfo};
 
+/// 记录natives文件夹中各jar的sha1及其解压出的文件
+const NATIVES_MANIFEST: &str = ".cemcl-natives.json";
+
+/// 多个natives可能同时下载完成，解压和读写清单时需要加锁
+static EXTRACT_LOCK: Mutex<()> = Mutex::new(());
+
+/// Fabric的maven仓库，其libraries没有downloads
+pub(crate) const FABRIC_MAVEN: &str = "https://maven.fabricmc.net/";
+
+/// 由group:artifact:version[:classifier][@ext]获取maven中的相对路径
+pub(crate) fn maven_path(name: &str) -> Option<String> {
+    let (name, ext) = name.split_once('@').unwrap_or((name, "jar"));
+    let split: Vec<&str> = name.split(":").collect();
+    if split.len() < 3 {
+        return None;
+    }
+    let mut path = split[0].replace('.', "/") + "/" + split[1] + "/" + split[2] + "/";
+    path = path + split[1] + "-" + split[2];
+    if let Some(classifier) = split.get(3) {
+        path = path + "-" + classifier;
+    }
+    Some(path + "." + ext)
+}
+
 /// 下载library
 fn download_lib(save_path: &str, node: &Value, mirror: &str) -> Result<TaskInfo, DownloadError> {
     let dir = get_parent_dir(&save_path);
@@ -25,59 +54,139 @@ fn download_lib(save_path: &str, node: &Value, mirror: &str) -> Result<TaskInfo,
     })
 }
 
+/// 获取library的extract.exclude，没有extract时返回None
+fn get_exclude(node: &Value) -> Option<Vec<String>> {
+    if !node["extract"].is_object() {
+        return None;
+    }
+    Some(
+        node["extract"]["exclude"]
+            .as_array()
+            .map(|list| {
+                list.iter()
+                    .filter_map(|item| item.as_str().map(|s| s.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default(),
+    )
+}
+
+/// 下载完成后解压natives
+fn extract_on_finish(
+    natives_dir: &str,
+    save_path: &str,
+    exclude: Option<Vec<String>>,
+) -> Option<Box<dyn Fn() + Send + Sync>> {
+    let (natives_dir, save_path) = (natives_dir.to_string(), save_path.to_string());
+    Some(Box::new(move || {
+        if extract_lib(&natives_dir, &save_path, exclude.as_ref()).is_err() {
+            error!("Failed to extract natives from {save_path}.");
+        }
+    }))
+}
+
+/// 获取旧版本natives的classifier，不是旧版本natives时返回None
+fn old_natives<'a>(node: &'a Value, os: &str) -> Result<Option<&'a Value>, DownloadError> {
+    if !node["natives"][os].is_string() || !node["downloads"]["classifiers"].is_object() {
+        return Ok(None);
+    }
+    let arch = if env::ARCH.contains("64") { "64" } else { "32" };
+    let key = node["natives"][os]
+        .as_str()
+        .ok_or(DownloadError::DataInvalid)?
+        .replace("${arch}", arch);
+    Ok(Some(&node["downloads"]["classifiers"][&key]))
+}
+
+/// 新版本的natives是单独的library
+fn is_natives_artifact(node: &Value) -> Result<bool, DownloadError> {
+    let name = node["name"].as_str().ok_or(DownloadError::DataInvalid)?;
+    Ok(name
+        .split(":")
+        .last()
+        .ok_or(DownloadError::DataInvalid)?
+        .contains("natives"))
+}
+
+/// 获取natives文件夹，node: mc json["libraries"]
+/// 文件夹名包含各natives的sha1，natives变化时使用新的文件夹，正在运行的游戏加载的文件不会被替换
+pub(crate) fn natives_dir(node: &Value, version_dir: &str) -> Result<String, DownloadError> {
+    let os = if env::OS == "macOS" { "osx" } else { env::OS };
+    let mut natives = Vec::new();
+    for node in node.as_array().ok_or(DownloadError::DataInvalid)? {
+        if node["rules"].is_array() && !check_rules(&node["rules"]) {
+            continue;
+        }
+        let mut files = Vec::new();
+        if let Some(file) = old_natives(node, os)? {
+            files.push(file);
+        }
+        if node["downloads"]["artifact"].is_object() && is_natives_artifact(node)? {
+            files.push(&node["downloads"]["artifact"]);
+        }
+        for file in files {
+            // 没有sha1时使用路径
+            let id = file["sha1"]
+                .as_str()
+                .or(file["path"].as_str())
+                .unwrap_or("");
+            natives.push(format!("{id}{}", node["extract"]));
+        }
+    }
+    let key = get_str_sha1(&natives.join("\n"));
+    Ok(version_dir.to_string() + "/natives-" + os + "-" + env::ARCH + "-" + &key[..8])
+}
+
+/// 删除版本文件夹中其他的natives文件夹，包括解压残留的临时文件夹
+/// 只能在该版本没有运行时调用，当前natives文件夹的临时文件夹可能正被其他进程使用，不删除
+pub(crate) fn remove_old_natives(natives_dir: &str) -> io::Result<()> {
+    let version_dir = get_parent_dir(natives_dir);
+    let current = natives_dir.rsplit('/').next().unwrap_or(natives_dir);
+    for entry in read_dir(&version_dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.starts_with("natives-") || name.starts_with(current) || !entry.path().is_dir() {
+            continue;
+        }
+        info!("Removing old natives {name}.");
+        remove_dir_all(entry.path())?;
+    }
+    Ok(())
+}
+
 /// 下载libraries，node: mc json["libraries"]，返回Tasks
+/// natives_dir: 由natives_dir()获取的natives文件夹
 pub fn download_libraries(
     node: &Value,
     path: &str,
-    game_dir: &str,
+    natives_dir: &str,
     mirror: &str,
     fabric_mirror: &str,
 ) -> Result<Vec<DownloadTask>, DownloadError> {
-    let mut c = 0;
     let mut tasks = Vec::new();
-    for item in node.as_array().ok_or(DownloadError::DataInvalid)? {
-        let (node, path, game_dir, mirror, id) = (
-            item.clone(),
-            path.to_string(),
-            game_dir.to_string(),
-            mirror.to_string(),
-            c.clone(),
-        );
-        let lib_dir = path.to_string() + "/libraries";
-        let os = if env::OS == "macOS" { "osx" } else { env::OS };
-        let natives_dir = game_dir.to_string() + "/natives-" + os + "-" + env::ARCH;
+    let lib_dir = path.to_string() + "/libraries";
+    let os = if env::OS == "macOS" { "osx" } else { env::OS };
+    for node in node.as_array().ok_or(DownloadError::DataInvalid)? {
         if node["rules"].is_array() {
             if !check_rules(&node["rules"]) {
                 continue;
             }
         }
+        let exclude = get_exclude(node);
         // Add natives for old versions
-        if node["natives"][os].is_string() && node["downloads"]["classifiers"].is_object() {
-            let arch = if env::ARCH.contains("64") { "64" } else { "32" };
-            let key = node["natives"][os]
-                .as_str()
-                .ok_or(std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    "Invaild data",
-                ))?
-                .replace("${arch}", arch);
-            let node = &node["downloads"]["classifiers"][&key];
+        if let Some(node) = old_natives(node, os)? {
             let save_path =
                 lib_dir.clone() + "/" + node["path"].as_str().ok_or(DownloadError::DataInvalid)?; // 储存位置
             if !exists(&save_path)? {
                 let task_info = download_lib(&save_path, node, &mirror)?;
-                let natives_dir_clone = natives_dir.clone();
                 tasks.push(DownloadTask {
                     url: task_info.url,
                     save_path: task_info.save_path,
-                    on_finish: Some(Box::new(move || {
-                        extract_lib(&natives_dir_clone, &save_path, &id.to_string());
-                    })),
+                    on_finish: extract_on_finish(natives_dir, &save_path, exclude.clone()),
                 });
             } else {
                 // TODO: check hash
-                let natives_dir_clone = natives_dir.clone();
-                extract_lib(&natives_dir_clone, &save_path, &id.to_string());
+                extract_lib(natives_dir, &save_path, exclude.as_ref())?;
             }
         }
         if node["downloads"]["artifact"].is_object() {
@@ -86,99 +195,240 @@ pub fn download_libraries(
                 + node["downloads"]["artifact"]["path"]
                     .as_str()
                     .ok_or(DownloadError::DataInvalid)?;
-            if !exists(&save_path)? {
+            let is_natives = is_natives_artifact(node)?;
+            if tasks.iter().any(|task| task.save_path == save_path) {
+                // 1.14~1.18的natives与本体为同一个jar，只下载一次
+            } else if !exists(&save_path)? {
                 let task_info = download_lib(&save_path, &node["downloads"]["artifact"], &mirror)?;
-                // Add natives
-                let name: Vec<&str> = node["name"]
-                    .as_str()
-                    .ok_or(DownloadError::DataInvalid)?
-                    .split(":")
-                    .collect();
-                let name = name.last().ok_or(DownloadError::DataInvalid)?;
-                if name.contains("natives") {
-                    let natives_dir_clone = natives_dir.clone();
-                    tasks.push(DownloadTask {
-                        url: task_info.url,
-                        save_path: task_info.save_path,
-                        on_finish: Some(Box::new(move || {
-                            extract_lib(&natives_dir_clone, &save_path, &id.to_string());
-                        })),
-                    });
+                let on_finish = if is_natives {
+                    extract_on_finish(natives_dir, &save_path, exclude)
                 } else {
-                    tasks.push(DownloadTask {
-                        url: task_info.url,
-                        save_path: task_info.save_path,
-                        on_finish: None,
-                    });
-                }
-            } else {
-                let natives_dir_clone = natives_dir.clone();
-                extract_lib(&natives_dir_clone, &save_path, &id.to_string());
+                    None
+                };
+                tasks.push(DownloadTask {
+                    url: task_info.url,
+                    save_path: task_info.save_path,
+                    on_finish,
+                });
+            } else if is_natives {
+                extract_lib(natives_dir, &save_path, exclude.as_ref())?;
             }
         } else {
             if let Some(url) = node["url"].as_str() {
-                if url == "https://maven.fabricmc.net/" {
+                if url == FABRIC_MAVEN {
                     // Fabric
-                    let mut path = String::new();
                     let name = node["name"].as_str().ok_or(DownloadError::DataInvalid)?;
-                    let split_1: Vec<&str> = name.split(":").collect();
-                    let split_2: Vec<&str> = split_1[0].split(".").collect();
-                    for name in split_2 {
-                        path = path + name + "/";
-                    }
-                    for i in 1..split_1.len() {
-                        let name = split_1[i];
-                        path = path + name + "/";
-                    }
-                    path = path + split_1[1] + "-" + split_1[2] + ".jar";
-                    if !exists(&path)? {
+                    let path = maven_path(name).ok_or(DownloadError::DataInvalid)?;
+                    let local_path = lib_dir.clone() + "/" + &path;
+                    if !exists(&local_path)? {
                         let url = fabric_mirror.to_string() + "/" + &path;
-                        let local_path = lib_dir.clone() + "/" + &path;
                         tasks.push(DownloadTask::new(url, local_path, None));
-                    } else {
-                        // TODO: check hash
                     }
                 }
             }
         }
-        c += 1;
     }
 
     Ok(tasks)
 }
 
+/// 是否为动态库
+fn is_native_lib(name: &str) -> bool {
+    // windows || macOS || linux
+    name.ends_with(".dll")
+        || name.ends_with(".dylib")
+        || name.ends_with(".jnilib")
+        || name.ends_with(".so")
+}
+
 /// 解压出natives
-pub fn extract_lib(natives_dir: &str, local_path: &str, id: &str) -> Result<(), DownloadError> {
+/// exclude: library的extract.exclude，为None时只解压动态库，且不保留目录结构
+/// natives文件夹可能正被运行中的游戏使用，jar未变化时只添加缺少的文件，不删除或替换已有文件
+pub fn extract_lib(
+    natives_dir: &str,
+    local_path: &str,
+    exclude: Option<&Vec<String>>,
+) -> Result<(), DownloadError> {
+    let _lock = EXTRACT_LOCK
+        .lock()
+        .map_err(|_| io::Error::other("natives lock poisoned"))?;
+
     // 目标natives文件夹
-    if !exists(&natives_dir)? {
-        std::fs::create_dir(&natives_dir)?;
-    }
-
-    // 解压用的临时文件夹
-    if exists(&("temp".to_string() + id))? {
-        std::fs::remove_dir_all("temp".to_string() + id)?;
-    }
-    std::fs::create_dir("temp".to_string() + id)?;
-
-    let mut zip = zip::ZipArchive::new(std::fs::File::open(local_path)?)
-        .map_err(|err| std::io::Error::from(err))?;
-    zip.extract("temp".to_string() + &id.to_string())
-        .map_err(|err| std::io::Error::from(err))?;
-    let files = list_file(&("temp".to_string() + &id.to_string()))?;
-    for name in files {
-        let format: Vec<&str> = name.split(".").collect();
-        let format = *format.last().ok_or(DownloadError::DataInvalid)?;
-        if !(format == "dll" || format == "dylib" || format == "so") {
-            // windows || macOS || linux
+    if !exists(natives_dir)? {
+        create_dir_all(natives_dir)?;
+    }
+
+    // 读取清单，文件完整时跳过
+    let manifest_path = natives_dir.to_string() + "/" + NATIVES_MANIFEST;
+    let mut manifest = if exists(&manifest_path)? {
+        serde_json::from_str::<Value>(&read_to_string(&manifest_path)?).unwrap_or(json!({}))
+    } else {
+        json!({})
+    };
+    if !manifest.is_object() {
+        manifest = json!({});
+    }
+    let sha1 = get_sha1(local_path)?;
+    // jar与清单记录的不同时，已有的文件需要替换
+    let changed = match manifest[local_path]["sha1"].as_str() {
+        Some(recorded) => recorded != sha1,
+        None => false,
+    };
+    if !changed && let Some(list) = manifest[local_path]["files"].as_array() {
+        let mut complete = true;
+        for file in list {
+            let file = file.as_str().ok_or(DownloadError::DataInvalid)?;
+            if !exists(natives_dir.to_string() + "/" + file)? {
+                complete = false;
+                break;
+            }
+        }
+        if complete {
+            return Ok(());
+        }
+    }
+
+    // 解压用的临时文件夹，避免其他启动器进程读到不完整的文件
+    let temp_dir = natives_dir.to_string() + "-temp-" + &uuid::Uuid::new_v4().simple().to_string();
+    create_dir_all(&temp_dir)?;
+
+    let mut zip = zip::ZipArchive::new(File::open(local_path)?).map_err(io::Error::from)?;
+    let mut files = Vec::new();
+    for i in 0..zip.len() {
+        let mut file = zip.by_index(i).map_err(io::Error::from)?;
+        if file.is_dir() {
             continue;
         }
-        let split: Vec<&str> = name.split("/").collect();
-        let file_name = split.last().ok_or(DownloadError::DataInvalid)?;
-        let target_path = natives_dir.to_string() + "/" + &file_name;
-        if !exists(&target_path)? {
-            copy(name, &target_path)?;
+        // 跳过不安全的路径
+        let Some(name) = file.enclosed_name() else {
+            continue;
+        };
+        let name = name.to_string_lossy().replace('\\', "/");
+        let target = match exclude {
+            Some(exclude) => {
+                if exclude
+                    .iter()
+                    .any(|prefix| name.starts_with(prefix.as_str()))
+                {
+                    continue;
+                }
+                name
+            }
+            None => {
+                if !is_native_lib(&name) {
+                    continue;
+                }
+                name.rsplit('/').next().unwrap_or(&name).to_string()
+            }
+        };
+        if files.contains(&target) {
+            continue;
+        }
+        if changed || !exists(natives_dir.to_string() + "/" + &target)? {
+            let temp_path = temp_dir.clone() + "/" + &target;
+            let dir = get_parent_dir(&temp_path);
+            if !exists(&dir)? {
+                create_dir_all(&dir)?;
+            }
+            io::copy(&mut file, &mut File::create(&temp_path)?)?;
         }
+        files.push(target);
     }
-    remove_dir_all("temp".to_string() + &id.to_string())?;
+
+    // 移动到natives文件夹，jar未变化时已存在的文件由其他进程解压，内容相同，不替换
+    for file in &files {
+        let temp_path = temp_dir.clone() + "/" + file;
+        let target_path = natives_dir.to_string() + "/" + file;
+        if !exists(&temp_path)? || (!changed && exists(&target_path)?) {
+            continue;
+        }
+        let dir = get_parent_dir(&target_path);
+        if !exists(&dir)? {
+            create_dir_all(&dir)?;
+        }
+        if let Err(err) = rename(&temp_path, &target_path) {
+            // 替换失败时文件可能正被使用，不能记录为新的jar
+            if changed || !exists(&target_path)? {
+                remove_dir_all(&temp_dir)?;
+                return Err(err.into());
+            }
+        }
+    }
+    remove_dir_all(&temp_dir)?;
+
+    manifest[local_path] = json!({ "sha1": sha1, "files": files });
+    write(&manifest_path, manifest.to_string())?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn libraries(sha1: &str) -> Value {
+        json!([
+            {
+                "downloads": {
+                    "artifact": {
+                        "path": "org/lwjgl/lwjgl/3.3.3/lwjgl-3.3.3.jar",
+                        "sha1": "29589b5f87ed335a6c7e7ee6a5775f81f97ecb84"
+                    }
+                },
+                "name": "org.lwjgl:lwjgl:3.3.3"
+            },
+            {
+                "downloads": {
+                    "artifact": {
+                        "path": "org/lwjgl/lwjgl/3.3.3/lwjgl-3.3.3-natives-linux.jar",
+                        "sha1": sha1
+                    }
+                },
+                "name": "org.lwjgl:lwjgl:3.3.3:natives-linux"
+            }
+        ])
+    }
+
+    #[test]
+    fn natives_dir_follows_natives() {
+        let dir = natives_dir(&libraries("aaaa"), "versions/1.21").unwrap_or_default();
+        assert!(dir.starts_with("versions/1.21/natives-"));
+        let same = natives_dir(&libraries("aaaa"), "versions/1.21").unwrap_or_default();
+        assert_eq!(dir, same);
+        let changed = natives_dir(&libraries("bbbb"), "versions/1.21").unwrap_or_default();
+        assert_ne!(dir, changed);
+    }
+
+    #[test]
+    fn remove_old_natives_keeps_current() {
+        let version_dir = std::env::temp_dir()
+            .join("cemcl-natives-test-".to_string() + &uuid::Uuid::new_v4().simple().to_string())
+            .to_string_lossy()
+            .replace('\\', "/");
+        let current = version_dir.clone() + "/natives-linux-x86_64-aaaaaaaa";
+        for dir in [
+            current.clone(),
+            current.clone() + "-temp-1234",
+            version_dir.clone() + "/natives-linux-x86_64-bbbbbbbb",
+            version_dir.clone() + "/natives-linux-x86_64-bbbbbbbb-temp-5678",
+            version_dir.clone() + "/saves",
+        ] {
+            create_dir_all(dir).unwrap();
+        }
+        remove_old_natives(&current).unwrap();
+
+        let mut names: Vec<String> = read_dir(&version_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        remove_dir_all(&version_dir).unwrap();
+        assert_eq!(
+            names,
+            [
+                "natives-linux-x86_64-aaaaaaaa",
+                "natives-linux-x86_64-aaaaaaaa-temp-1234",
+                "saves"
+            ]
+        );
+    }
+}
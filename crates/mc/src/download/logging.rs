@@ -0,0 +1,116 @@
+//! Download logging config
+
+use log::{error, warn};
+use serde_json::Value;
+use std::fs::{create_dir_all, exists, read_to_string, remove_file, write};
+
+use utils::check_sha1;
+
+use super::{DownloadError, DownloadTask};
+
+/// 启动器提供的日志配置文件名
+const LAUNCHER_LOG_CONFIG_ID: &str = "cemcl-xml.xml";
+
+/// 启动器提供的日志配置，每行输出一条完整的XML事件，便于启动器解析
+const LAUNCHER_LOG_CONFIG: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Configuration status="WARN">
+    <Appenders>
+        <Console name="SysOut" target="SYSTEM_OUT">
+            <XMLLayout compact="true" eventEol="true" />
+        </Console>
+        <RollingRandomAccessFile name="File" fileName="logs/latest.log" filePattern="logs/%d{yyyy-MM-dd}-%i.log.gz">
+            <PatternLayout pattern="[%d{HH:mm:ss}] [%t/%level]: %msg{nolookups}%n" />
+            <Policies>
+                <TimeBasedTriggeringPolicy />
+                <OnStartupTriggeringPolicy />
+            </Policies>
+        </RollingRandomAccessFile>
+    </Appenders>
+    <Loggers>
+        <Root level="info">
+            <filters>
+                <MarkerFilter marker="NETWORK_PACKETS" onMatch="DENY" onMismatch="NEUTRAL" />
+            </filters>
+            <AppenderRef ref="SysOut" />
+            <AppenderRef ref="File" />
+        </Root>
+    </Loggers>
+</Configuration>
+"#;
+
+/// 下载完成后检查sha1，不一致时删除，启动时去掉其参数，下次启动时重新下载
+fn check_on_finish(save_path: &str, sha1: &str) -> Option<Box<dyn Fn() + Send + Sync>> {
+    let (save_path, sha1) = (save_path.to_string(), sha1.to_string());
+    Some(Box::new(move || match check_sha1(&save_path, &sha1) {
+        Ok(true) => {}
+        Ok(false) => {
+            error!("The sha1 of {save_path} does not match.");
+            if let Err(e) = remove_file(&save_path) {
+                error!("{e}");
+            }
+        }
+        Err(e) => error!("Failed to check {save_path}. Reason: {e}"),
+    }))
+}
+
+/// 获取日志配置，node: mc json["logging"]["client"]，返回JVM参数和下载任务
+/// launcher_config为true时使用启动器提供的配置代替官方配置
+pub fn download_log_config(
+    node: &Value,
+    path: &str,
+    mirror: &str,
+    launcher_config: bool,
+) -> Result<(Option<String>, Option<DownloadTask>), DownloadError> {
+    if !node.is_object() {
+        // 旧版本没有日志配置
+        return Ok((None, None));
+    }
+
+    let dir = path.to_string() + "/assets/log_configs";
+    if !exists(&dir)? {
+        create_dir_all(&dir)?;
+    }
+
+    let argument = node["argument"]
+        .as_str()
+        .unwrap_or("-Dlog4j.configurationFile=${path}");
+
+    if launcher_config {
+        let save_path = dir + "/" + LAUNCHER_LOG_CONFIG_ID;
+        if !exists(&save_path)? || read_to_string(&save_path)? != LAUNCHER_LOG_CONFIG {
+            write(&save_path, LAUNCHER_LOG_CONFIG)?;
+        }
+        return Ok((Some(argument.replace("${path}", &save_path)), None));
+    }
+
+    let file = &node["file"];
+    let id = file["id"].as_str().ok_or(DownloadError::DataInvalid)?;
+    let sha1 = file["sha1"].as_str().ok_or(DownloadError::DataInvalid)?;
+    // 与本体相同，下载源只替换piston-meta
+    let url = file["url"]
+        .as_str()
+        .ok_or(DownloadError::DataInvalid)?
+        .replace("https://piston-meta.mojang.com", mirror);
+    let save_path = dir + "/" + id;
+
+    let task = if !exists(&save_path)? {
+        Some(DownloadTask::new(
+            url,
+            save_path.clone(),
+            check_on_finish(&save_path, sha1),
+        ))
+    } else if !check_sha1(&save_path, sha1)? {
+        warn!("{save_path} is broken, download it again.");
+        // 下载器会追加到已存在的文件
+        remove_file(&save_path)?;
+        Some(DownloadTask::new(
+            url,
+            save_path.clone(),
+            check_on_finish(&save_path, sha1),
+        ))
+    } else {
+        None
+    };
+
+    Ok((Some(argument.replace("${path}", &save_path)), task))
+}
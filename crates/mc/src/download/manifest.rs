@@ -29,6 +29,11 @@ pub struct Forge {
     pub modified: String,
 }
 
+#[derive(Clone)]
+pub struct NeoForge {
+    pub version: String,
+}
+
 fn mc_type(s: &str) -> Option<MCType> {
     match s {
         "release" => Some(MCType::Release),
@@ -105,6 +110,55 @@ pub async fn list_forge(mcversion: &String) -> Result<Vec<Forge>, DownloadError>
     Ok(forge_list)
 }
 
+/// NeoForge在maven中的artifact，1.20.1只有net.neoforged:forge
+fn neoforge_artifact(mcversion: &str) -> &'static str {
+    if mcversion == "1.20.1" {
+        "forge"
+    } else {
+        "neoforge"
+    }
+}
+
+/// 该MC版本对应的NeoForge版本前缀
+/// 如1.20.4 -> 20.4.，1.21 -> 21.0.，26.1 -> 26.1.0.，1.20.1 -> 1.20.1-
+fn neoforge_prefix(mcversion: &str) -> String {
+    if mcversion == "1.20.1" {
+        return String::from("1.20.1-");
+    }
+    let mut split: Vec<&str> = mcversion.split(".").collect();
+    if split[0] == "1" {
+        split.remove(0);
+        split.resize(2, "0");
+    } else {
+        split.resize(3, "0");
+    }
+    split.join(".") + "."
+}
+
+/// 获取NeoForge列表，读取maven-metadata.xml，新版本在前
+/// mirror: NeoForge的maven仓库
+pub async fn list_neoforge(mcversion: &str, mirror: &str) -> Result<Vec<NeoForge>, DownloadError> {
+    let url = format!(
+        "{mirror}/net/neoforged/{artifact}/maven-metadata.xml",
+        artifact = neoforge_artifact(mcversion)
+    );
+    let text = reqwest::get(url).await?.error_for_status()?.text().await?;
+
+    let prefix = neoforge_prefix(mcversion);
+    let mut neoforge_list: Vec<NeoForge> = text
+        .split("<version>")
+        .skip(1)
+        .map(|item| item.split("</version>").next().unwrap_or_default().trim())
+        .filter(|version| version.starts_with(&prefix))
+        .map(|version| NeoForge {
+            version: version.to_string(),
+        })
+        .collect();
+    neoforge_list.reverse();
+
+    Ok(neoforge_list)
+}
+
 /// 获取下载列表
 pub async fn list_game(path: String) -> Result<Vec<MCDL>, DownloadError> {
     let mut game_list = Vec::new();
@@ -190,6 +244,24 @@ pub fn download_forge(mcversion: &str, forge: Forge, mirror: &str) -> TaskInfo {
     }
 }
 
+pub fn download_neoforge(mcversion: &str, neoforge: NeoForge, mirror: &str) -> TaskInfo {
+    let artifact = neoforge_artifact(mcversion);
+    let neoforge_url = format!(
+        "{mirror}/net/neoforged/{artifact}/{version}/{artifact}-{version}-installer.jar",
+        version = neoforge.version
+    );
+
+    let neoforge_path = format!(
+        "temp-neoforge-{version}/{artifact}-{version}-installer.jar",
+        version = neoforge.version
+    );
+
+    TaskInfo {
+        url: neoforge_url,
+        save_path: neoforge_path,
+    }
+}
+
 pub async fn download_mc(mc_path: &str, mcdl: MCDL) -> Result<(), DownloadError> {
     let dir = mc_path.to_string() + "/versions/" + &mcdl.version;
     let path = dir.clone() + "/" + &mcdl.version + ".json";
@@ -2,10 +2,13 @@ use crate::download::DownloadError::{DeserializeError, IOError, ReqwestError};
 
 mod assets;
 mod libraries;
+mod logging;
 pub mod manifest;
 
-pub use assets::download_assets;
+pub use assets::{download_assets, get_game_assets};
 pub use libraries::download_libraries;
+pub(crate) use libraries::{FABRIC_MAVEN, maven_path, natives_dir, remove_old_natives};
+pub use logging::download_log_config;
 
 pub struct TaskInfo {
     pub url: String,
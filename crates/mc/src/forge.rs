@@ -0,0 +1,449 @@
+//! Forge和NeoForge安装
+//! 不运行安装器的界面，读取其中的install_profile.json，下载libraries并执行processors
+
+use log::{error, info};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::env::consts as env;
+use std::fs::{File, create_dir_all, exists, read_to_string, remove_file, write};
+use std::io::{self, Read};
+use std::process::Command;
+
+use utils::{check_rules, check_sha1, get_parent_dir};
+
+use crate::download::{DownloadTask, maven_path};
+
+/// Mojang的libraries
+const MOJANG_LIBRARIES: &str = "https://libraries.minecraft.net/";
+
+/// Forge的maven仓库，旧版本的安装器中为files.minecraftforge.net/maven，更旧的使用http
+const FORGE_MAVEN: [&str; 3] = [
+    "https://maven.minecraftforge.net",
+    "https://files.minecraftforge.net/maven",
+    "http://files.minecraftforge.net/maven",
+];
+
+/// NeoForge的maven仓库
+const NEOFORGE_MAVEN: &str = "https://maven.neoforged.net/releases";
+
+pub enum InstallError {
+    /// processor输出的文件sha1不符，为其路径
+    ChecksumMismatch(String),
+    DataInvalid,
+    DeserializeError(serde_json::Error),
+    IOError(io::Error),
+    /// processor执行失败，为其jar
+    ProcessorFailed(String),
+    ZipError(zip::result::ZipError),
+}
+
+impl From<io::Error> for InstallError {
+    fn from(value: io::Error) -> Self {
+        InstallError::IOError(value)
+    }
+}
+
+impl From<serde_json::Error> for InstallError {
+    fn from(value: serde_json::Error) -> Self {
+        InstallError::DeserializeError(value)
+    }
+}
+
+impl From<zip::result::ZipError> for InstallError {
+    fn from(value: zip::result::ZipError) -> Self {
+        InstallError::ZipError(value)
+    }
+}
+
+impl std::fmt::Display for InstallError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InstallError::ChecksumMismatch(s) => write!(f, "The checksum of {s} does not match"),
+            InstallError::DataInvalid => write!(f, "The installer is invalid"),
+            InstallError::DeserializeError(err) => write!(f, "{err}"),
+            InstallError::IOError(err) => write!(f, "{err}"),
+            InstallError::ProcessorFailed(s) => write!(f, "Processor {s} failed"),
+            InstallError::ZipError(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+/// 下载地址替换为下载源
+fn mirror_url(url: &str) -> String {
+    let mut url = url.replace(MOJANG_LIBRARIES, "{libraries_source}/");
+    for maven in FORGE_MAVEN {
+        url = url.replace(maven, "{forge_source}/maven");
+    }
+    url.replace(NEOFORGE_MAVEN, "{neoforge_source}")
+}
+
+/// 去掉首尾的括号或引号，不是以其包围时返回None
+fn strip_brackets(s: &str, start: char, end: char) -> Option<&str> {
+    s.strip_prefix(start)?.strip_suffix(end)
+}
+
+/// 读取安装器中的json
+fn read_json(zip: &mut zip::ZipArchive<File>, name: &str) -> Result<Value, InstallError> {
+    let mut text = String::new();
+    zip.by_name(name)?.read_to_string(&mut text)?;
+    Ok(serde_json::from_str::<Value>(&text)?)
+}
+
+/// 从jar的MANIFEST.MF读取主类
+fn get_main_class(jar: &str) -> Result<String, InstallError> {
+    let mut zip = zip::ZipArchive::new(File::open(jar)?)?;
+    let mut manifest = String::new();
+    zip.by_name("META-INF/MANIFEST.MF")?
+        .read_to_string(&mut manifest)?;
+    manifest
+        .lines()
+        .find_map(|line| line.strip_prefix("Main-Class:"))
+        .map(|class| class.trim().to_string())
+        .ok_or(InstallError::ProcessorFailed(jar.to_string()))
+}
+
+/// NeoForge的安装器与Forge格式相同
+pub struct ForgeInstaller {
+    /// 安装器jar的路径
+    installer_path: String,
+    /// install_profile.json
+    profile: Value,
+    /// 安装后的版本json
+    version: Value,
+}
+
+impl ForgeInstaller {
+    /// 读取安装器，1.12.2及以前的安装器中，版本json位于install_profile.json的versionInfo
+    pub fn new(installer_path: &str) -> Result<Self, InstallError> {
+        let mut zip = zip::ZipArchive::new(File::open(installer_path)?)?;
+        let profile = read_json(&mut zip, "install_profile.json")?;
+        let version = if profile["versionInfo"].is_object() {
+            profile["versionInfo"].clone()
+        } else {
+            let name = profile["json"].as_str().unwrap_or("/version.json");
+            read_json(&mut zip, name.trim_start_matches('/'))?
+        };
+        Ok(Self {
+            installer_path: installer_path.to_string(),
+            profile,
+            version,
+        })
+    }
+
+    /// 安装后的版本id，即版本json中的id，如1.20.1-forge-47.2.0
+    pub fn get_id(&self) -> Result<&str, InstallError> {
+        self.version["id"].as_str().ok_or(InstallError::DataInvalid)
+    }
+
+    /// 对应的MC版本
+    pub fn get_minecraft(&self) -> Result<&str, InstallError> {
+        self.profile["minecraft"]
+            .as_str()
+            .or(self.profile["install"]["minecraft"].as_str())
+            .ok_or(InstallError::DataInvalid)
+    }
+
+    /// 写入版本json，解压安装器中的libraries，返回需要下载的libraries
+    /// 有processors时还需要原版的jar，其json应已下载
+    /// Note that the download sources should be replaced
+    /// {forge_source}, {game_source}, {libraries_source}, {neoforge_source}
+    pub fn prepare(&self, game_path: &str) -> Result<Vec<DownloadTask>, InstallError> {
+        let id = self.get_id()?;
+        let minecraft = self.get_minecraft()?;
+        let lib_dir = game_path.to_string() + "/libraries";
+
+        // 版本json
+        let dir = format!("{game_path}/versions/{id}");
+        if !exists(&dir)? {
+            create_dir_all(&dir)?;
+        }
+        let mut version = self.version.clone();
+        if !version["inheritsFrom"].is_string() {
+            // 很旧的版本没有inheritsFrom
+            version["inheritsFrom"] = json!(minecraft);
+        }
+        write(format!("{dir}/{id}.json"), version.to_string())?;
+
+        self.i_extract_libraries(&lib_dir)?;
+
+        // processors和游戏需要的libraries
+        let mut tasks: Vec<DownloadTask> = Vec::new();
+        let libraries = self.profile["libraries"]
+            .as_array()
+            .into_iter()
+            .chain(self.version["libraries"].as_array())
+            .flatten();
+        for node in libraries {
+            match Self::i_library_task(&lib_dir, node)? {
+                Some(task) if tasks.iter().all(|t| t.save_path != task.save_path) => {
+                    tasks.push(task)
+                }
+                _ => {}
+            }
+        }
+
+        // 原版jar
+        if self.profile["processors"].is_array() {
+            let mc_dir = format!("{game_path}/versions/{minecraft}");
+            let jar_path = format!("{mc_dir}/{minecraft}.jar");
+            if !exists(&jar_path)? {
+                let json = serde_json::from_str::<Value>(&read_to_string(format!(
+                    "{mc_dir}/{minecraft}.json"
+                ))?)?;
+                let url = json["downloads"]["client"]["url"]
+                    .as_str()
+                    .ok_or(InstallError::DataInvalid)?
+                    .replace("https://piston-meta.mojang.com", "{game_source}");
+                tasks.push(DownloadTask::new(url, jar_path, None));
+            }
+        }
+
+        Ok(tasks)
+    }
+
+    /// 执行客户端的processors，java_path: 用于执行processors的Java
+    /// on_progress: 已完成数量和总数
+    pub fn run_processors(
+        &self,
+        game_path: &str,
+        java_path: &str,
+        on_progress: impl Fn(usize, usize),
+    ) -> Result<(), InstallError> {
+        let processors: Vec<&Value> = self.profile["processors"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|processor| match processor["sides"].as_array() {
+                Some(sides) => sides.iter().any(|side| side.as_str() == Some("client")),
+                None => true,
+            })
+            .collect();
+        if processors.is_empty() {
+            return Ok(());
+        }
+
+        let lib_dir = game_path.to_string() + "/libraries";
+        let data = self.i_get_data(game_path)?;
+        let sep = if env::OS == "windows" { ";" } else { ":" };
+        for (index, processor) in processors.iter().enumerate() {
+            on_progress(index, processors.len());
+
+            let mut outputs = Vec::new();
+            if let Some(map) = processor["outputs"].as_object() {
+                for (path, sha1) in map {
+                    let sha1 = sha1.as_str().ok_or(InstallError::DataInvalid)?;
+                    outputs.push((
+                        Self::i_replace(path, &data, &lib_dir)?,
+                        Self::i_replace(sha1, &data, &lib_dir)?,
+                    ));
+                }
+            }
+            // 重新安装时跳过已完成的processor
+            if !outputs.is_empty() && Self::i_check_outputs(&outputs)? {
+                continue;
+            }
+
+            let name = processor["jar"].as_str().ok_or(InstallError::DataInvalid)?;
+            let jar = Self::i_artifact_path(&lib_dir, name)?;
+            let mut classpath = vec![jar.clone()];
+            for item in processor["classpath"].as_array().into_iter().flatten() {
+                let item = item.as_str().ok_or(InstallError::DataInvalid)?;
+                classpath.push(Self::i_artifact_path(&lib_dir, item)?);
+            }
+            let mut args = Vec::new();
+            for arg in processor["args"].as_array().into_iter().flatten() {
+                let arg = arg.as_str().ok_or(InstallError::DataInvalid)?;
+                args.push(Self::i_replace(arg, &data, &lib_dir)?);
+            }
+
+            info!("Run processor {name}.");
+            let output = Command::new(java_path)
+                .arg("-cp")
+                .arg(classpath.join(sep))
+                .arg(get_main_class(&jar)?)
+                .args(&args)
+                .output()?;
+            if !output.status.success() {
+                error!(
+                    "Processor {name} exited with {}.\n{}",
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr)
+                );
+                return Err(InstallError::ProcessorFailed(name.to_string()));
+            }
+            for (path, sha1) in &outputs {
+                if !check_sha1(path, sha1)? {
+                    return Err(InstallError::ChecksumMismatch(path.clone()));
+                }
+            }
+        }
+        on_progress(processors.len(), processors.len());
+
+        Ok(())
+    }
+
+    /// 解压安装器中的libraries
+    fn i_extract_libraries(&self, lib_dir: &str) -> Result<(), InstallError> {
+        let mut zip = zip::ZipArchive::new(File::open(&self.installer_path)?)?;
+
+        // 旧版本只有一个universal jar
+        if let (Some(file_path), Some(name)) = (
+            self.profile["install"]["filePath"].as_str(),
+            self.profile["install"]["path"].as_str(),
+        ) {
+            let path = Self::i_artifact_path(lib_dir, name)?;
+            let dir = get_parent_dir(&path);
+            if !exists(&dir)? {
+                create_dir_all(&dir)?;
+            }
+            io::copy(&mut zip.by_name(file_path)?, &mut File::create(&path)?)?;
+            return Ok(());
+        }
+
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i)?;
+            if file.is_dir() {
+                continue;
+            }
+            // 跳过不安全的路径
+            let Some(name) = file.enclosed_name() else {
+                continue;
+            };
+            let name = name.to_string_lossy().replace('\\', "/");
+            let Some(name) = name.strip_prefix("maven/") else {
+                continue;
+            };
+            let path = lib_dir.to_string() + "/" + name;
+            let dir = get_parent_dir(&path);
+            if !exists(&dir)? {
+                create_dir_all(&dir)?;
+            }
+            io::copy(&mut file, &mut File::create(&path)?)?;
+        }
+        Ok(())
+    }
+
+    /// library的下载任务，已存在或由processors生成时返回None
+    fn i_library_task(lib_dir: &str, node: &Value) -> Result<Option<DownloadTask>, InstallError> {
+        if node["rules"].is_array() && !check_rules(&node["rules"]) {
+            return Ok(None);
+        }
+        // 旧版本中只有服务端需要的library
+        if node["clientreq"].as_bool() == Some(false) {
+            return Ok(None);
+        }
+        let artifact = &node["downloads"]["artifact"];
+        let (path, url) = if artifact.is_object() {
+            let path = artifact["path"].as_str().ok_or(InstallError::DataInvalid)?;
+            let url = artifact["url"].as_str().unwrap_or_default();
+            (lib_dir.to_string() + "/" + path, url.to_string())
+        } else {
+            // 旧版本的library只有maven仓库
+            let name = node["name"].as_str().ok_or(InstallError::DataInvalid)?;
+            let path = maven_path(name).ok_or(InstallError::DataInvalid)?;
+            let mut base = node["url"].as_str().unwrap_or(MOJANG_LIBRARIES).to_string();
+            if !base.ends_with('/') {
+                base.push('/');
+            }
+            (lib_dir.to_string() + "/" + &path, base + &path)
+        };
+        if url.is_empty() {
+            return Ok(None);
+        }
+
+        if exists(&path)? {
+            match artifact["sha1"].as_str() {
+                Some(sha1) if !check_sha1(&path, sha1)? => {
+                    // 下载器会追加到已存在的文件
+                    remove_file(&path)?;
+                }
+                _ => return Ok(None),
+            }
+        }
+        let dir = get_parent_dir(&path);
+        if !exists(&dir)? {
+            create_dir_all(&dir)?;
+        }
+        Ok(Some(DownloadTask::new(mirror_url(&url), path, None)))
+    }
+
+    /// processors的参数中可用的变量
+    fn i_get_data(&self, game_path: &str) -> Result<HashMap<String, String>, InstallError> {
+        let lib_dir = game_path.to_string() + "/libraries";
+        let minecraft = self.get_minecraft()?;
+        let mut data = HashMap::new();
+        let mut zip = zip::ZipArchive::new(File::open(&self.installer_path)?)?;
+        // 安装器中的文件解压到安装器所在的文件夹
+        let temp_dir = get_parent_dir(&self.installer_path);
+
+        for (key, value) in self.profile["data"].as_object().into_iter().flatten() {
+            let value = value["client"].as_str().ok_or(InstallError::DataInvalid)?;
+            let value = if let Some(name) = strip_brackets(value, '[', ']') {
+                Self::i_artifact_path(&lib_dir, name)?
+            } else if let Some(literal) = strip_brackets(value, '\'', '\'') {
+                literal.to_string()
+            } else if let Some(name) = value.strip_prefix('/') {
+                let path = temp_dir.clone() + "/" + name;
+                let dir = get_parent_dir(&path);
+                if !exists(&dir)? {
+                    create_dir_all(&dir)?;
+                }
+                io::copy(&mut zip.by_name(name)?, &mut File::create(&path)?)?;
+                path
+            } else {
+                value.to_string()
+            };
+            data.insert(key.clone(), value);
+        }
+
+        data.insert("INSTALLER".to_string(), self.installer_path.clone());
+        data.insert("LIBRARY_DIR".to_string(), lib_dir);
+        data.insert(
+            "MINECRAFT_JAR".to_string(),
+            format!("{game_path}/versions/{minecraft}/{minecraft}.jar"),
+        );
+        data.insert("MINECRAFT_VERSION".to_string(), minecraft.to_string());
+        data.insert("ROOT".to_string(), game_path.to_string());
+        data.insert("SIDE".to_string(), "client".to_string());
+        Ok(data)
+    }
+
+    /// 由group:artifact:version获取library的路径
+    fn i_artifact_path(lib_dir: &str, name: &str) -> Result<String, InstallError> {
+        let path = maven_path(name).ok_or(InstallError::DataInvalid)?;
+        Ok(lib_dir.to_string() + "/" + &path)
+    }
+
+    /// 替换参数，[artifact]为library的路径，{KEY}为data中的值
+    fn i_replace(
+        arg: &str,
+        data: &HashMap<String, String>,
+        lib_dir: &str,
+    ) -> Result<String, InstallError> {
+        if let Some(name) = strip_brackets(arg, '[', ']') {
+            return Self::i_artifact_path(lib_dir, name);
+        }
+
+        let mut result = String::new();
+        let mut rest = arg;
+        while let Some(start) = rest.find('{') {
+            let end = start + rest[start..].find('}').ok_or(InstallError::DataInvalid)?;
+            let value = data
+                .get(&rest[start + 1..end])
+                .ok_or(InstallError::DataInvalid)?;
+            result = result + &rest[..start] + value;
+            rest = &rest[end + 1..];
+        }
+        Ok(result + rest)
+    }
+
+    /// processor的输出是否都已存在且sha1一致
+    fn i_check_outputs(outputs: &[(String, String)]) -> Result<bool, InstallError> {
+        for (path, sha1) in outputs {
+            if !exists(path)? || !check_sha1(path, sha1)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
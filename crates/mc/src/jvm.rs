@@ -0,0 +1,412 @@
+//! JVM参数预设及内存设置
+//! mc::jvm 按Java版本和JVM实现筛选预设中的JVM参数，检查和自动分配内存
+
+use java::java::JavaInstallation;
+use serde_json::{Value, json};
+
+/// 默认使用的预设
+pub const DEFAULT_PRESET: &str = "vanilla";
+
+/// JVM实现，两者的垃圾回收器参数不同
+#[derive(Clone, Copy, PartialEq)]
+pub enum JvmImpl {
+    HotSpot,
+    OpenJ9,
+}
+
+impl JvmImpl {
+    /// 无法获取Java时按HotSpot处理
+    fn of(java: Option<&JavaInstallation>) -> Self {
+        match java {
+            Some(java) if java.is_openj9() => JvmImpl::OpenJ9,
+            _ => JvmImpl::HotSpot,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            JvmImpl::HotSpot => "hotspot",
+            JvmImpl::OpenJ9 => "openj9",
+        }
+    }
+
+    pub fn from_name(s: &str) -> Option<Self> {
+        match s {
+            "hotspot" => Some(JvmImpl::HotSpot),
+            "openj9" => Some(JvmImpl::OpenJ9),
+            _ => None,
+        }
+    }
+}
+
+/// 单条JVM参数及其适用的Java主版本范围和JVM实现
+#[derive(Clone)]
+pub struct JvmFlag {
+    pub flag: String,
+    /// 最低Java主版本，None表示不限
+    pub min_java: Option<u8>,
+    /// 最高Java主版本，None表示不限
+    pub max_java: Option<u8>,
+    /// 适用的JVM实现，None表示不限
+    pub vm: Option<JvmImpl>,
+}
+
+/// 命名的JVM参数预设
+#[derive(Clone)]
+pub struct JvmPreset {
+    pub name: String,
+    pub flags: Vec<JvmFlag>,
+}
+
+impl JvmFlag {
+    fn new(flag: &str) -> Self {
+        Self {
+            flag: flag.to_string(),
+            min_java: None,
+            max_java: None,
+            vm: None,
+        }
+    }
+
+    fn range(flag: &str, min_java: Option<u8>, max_java: Option<u8>) -> Self {
+        Self {
+            flag: flag.to_string(),
+            min_java,
+            max_java,
+            vm: None,
+        }
+    }
+
+    /// 只适用于HotSpot的参数，如-XX:+UseG1GC
+    fn hotspot(self) -> Self {
+        Self {
+            vm: Some(JvmImpl::HotSpot),
+            ..self
+        }
+    }
+
+    /// 只适用于OpenJ9的参数，如-Xgcpolicy:gencon
+    fn openj9(self) -> Self {
+        Self {
+            vm: Some(JvmImpl::OpenJ9),
+            ..self
+        }
+    }
+
+    /// 是否适用于该Java
+    /// 无法获取Java时，只使用不限版本的参数
+    fn is_supported(&self, java: Option<&JavaInstallation>) -> bool {
+        if self.vm.is_some_and(|vm| vm != JvmImpl::of(java)) {
+            return false;
+        }
+        let Some(java) = java else {
+            return self.min_java.is_none() && self.max_java.is_none();
+        };
+        let feature = java.get_version().get_feature();
+        self.min_java.is_none_or(|min| feature >= min)
+            && self.max_java.is_none_or(|max| feature <= max)
+    }
+
+    pub fn from_json(node: &Value) -> Option<Self> {
+        Some(Self {
+            flag: node["flag"].as_str()?.to_string(),
+            min_java: node["min_java"].as_u64().map(|v| v as u8),
+            max_java: node["max_java"].as_u64().map(|v| v as u8),
+            vm: node["vm"].as_str().and_then(JvmImpl::from_name),
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut node = json!({ "flag": self.flag });
+        if let Some(min) = self.min_java {
+            node["min_java"] = json!(min);
+        }
+        if let Some(max) = self.max_java {
+            node["max_java"] = json!(max);
+        }
+        if let Some(vm) = self.vm {
+            node["vm"] = json!(vm.as_str());
+        }
+        node
+    }
+}
+
+impl JvmPreset {
+    /// 获取适用于该Java的参数
+    pub fn get_flags(&self, java: Option<&JavaInstallation>) -> Vec<String> {
+        self.flags
+            .iter()
+            .filter(|flag| flag.is_supported(java))
+            .map(|flag| flag.flag.clone())
+            .collect()
+    }
+
+    pub fn from_json(node: &Value) -> Option<Self> {
+        Some(Self {
+            name: node["name"].as_str()?.to_string(),
+            flags: node["flags"]
+                .as_array()?
+                .iter()
+                .map(JvmFlag::from_json)
+                .collect::<Option<Vec<JvmFlag>>>()?,
+        })
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "flags": self.flags.iter().map(|flag| flag.to_json()).collect::<Vec<Value>>(),
+        })
+    }
+}
+
+/// 内置预设：vanilla, aikar, zgc, low-memory
+pub fn builtin_presets() -> Vec<JvmPreset> {
+    vec![
+        // 与官方启动器一致
+        JvmPreset {
+            name: DEFAULT_PRESET.to_string(),
+            flags: vec![
+                JvmFlag::new("-XX:+UnlockExperimentalVMOptions").hotspot(),
+                JvmFlag::new("-XX:+UseG1GC").hotspot(),
+                JvmFlag::new("-XX:G1NewSizePercent=20").hotspot(),
+                JvmFlag::new("-XX:G1ReservePercent=20").hotspot(),
+                JvmFlag::new("-XX:MaxGCPauseMillis=50").hotspot(),
+                JvmFlag::new("-XX:G1HeapRegionSize=32M").hotspot(),
+                JvmFlag::new("-XX:-OmitStackTraceInFastThrow").hotspot(),
+                JvmFlag::new("-Xgcpolicy:gencon").openj9(),
+            ],
+        },
+        // https://docs.papermc.io/paper/aikars-flags
+        JvmPreset {
+            name: "aikar".to_string(),
+            flags: vec![
+                JvmFlag::new("-XX:+UseG1GC").hotspot(),
+                JvmFlag::new("-XX:+ParallelRefProcEnabled").hotspot(),
+                JvmFlag::new("-XX:MaxGCPauseMillis=200").hotspot(),
+                JvmFlag::new("-XX:+UnlockExperimentalVMOptions").hotspot(),
+                JvmFlag::new("-XX:+DisableExplicitGC").hotspot(),
+                JvmFlag::new("-XX:+AlwaysPreTouch").hotspot(),
+                JvmFlag::new("-XX:G1NewSizePercent=30").hotspot(),
+                JvmFlag::new("-XX:G1MaxNewSizePercent=40").hotspot(),
+                JvmFlag::new("-XX:G1HeapRegionSize=8M").hotspot(),
+                JvmFlag::new("-XX:G1ReservePercent=20").hotspot(),
+                JvmFlag::new("-XX:G1HeapWastePercent=5").hotspot(),
+                JvmFlag::new("-XX:G1MixedGCCountTarget=4").hotspot(),
+                JvmFlag::new("-XX:InitiatingHeapOccupancyPercent=15").hotspot(),
+                JvmFlag::new("-XX:G1MixedGCLiveThresholdPercent=90").hotspot(),
+                // Java 20起已废弃
+                JvmFlag::range("-XX:G1RSetUpdatingPauseTimePercent=5", None, Some(19)).hotspot(),
+                JvmFlag::new("-XX:SurvivorRatio=32").hotspot(),
+                JvmFlag::new("-XX:+PerfDisableSharedMem").hotspot(),
+                JvmFlag::new("-XX:MaxTenuringThreshold=1").hotspot(),
+                JvmFlag::new("-XX:-OmitStackTraceInFastThrow").hotspot(),
+                JvmFlag::new("-Xgcpolicy:gencon").openj9(),
+                JvmFlag::new("-Xgc:concurrentScavenge").openj9(),
+                JvmFlag::new("-Xdisableexplicitgc").openj9(),
+            ],
+        },
+        // Java 21以下使用默认的垃圾回收器
+        JvmPreset {
+            name: "zgc".to_string(),
+            flags: vec![
+                JvmFlag::range("-XX:+UseZGC", Some(21), None).hotspot(),
+                // Java 23起默认启用分代ZGC
+                JvmFlag::range("-XX:+ZGenerational", Some(21), Some(22)).hotspot(),
+                JvmFlag::range("-XX:+AlwaysPreTouch", Some(21), None).hotspot(),
+                JvmFlag::new("-XX:-OmitStackTraceInFastThrow").hotspot(),
+                // OpenJ9没有ZGC，balanced的停顿较短
+                JvmFlag::new("-Xgcpolicy:balanced").openj9(),
+            ],
+        },
+        // 内存较小时，串行回收器的额外开销最少
+        JvmPreset {
+            name: "low-memory".to_string(),
+            flags: vec![
+                JvmFlag::new("-XX:+UseSerialGC").hotspot(),
+                JvmFlag::new("-XX:MinHeapFreeRatio=10").hotspot(),
+                JvmFlag::new("-XX:MaxHeapFreeRatio=30").hotspot(),
+                // Java 18起SerialGC支持字符串去重
+                JvmFlag::range("-XX:+UseStringDeduplication", Some(18), None).hotspot(),
+                JvmFlag::new("-XX:-OmitStackTraceInFastThrow").hotspot(),
+                // 减少空闲时的内存占用
+                JvmFlag::new("-Xtune:virtualized").openj9(),
+            ],
+        },
+    ]
+}
+
+/// 按名称查找预设，找不到时使用默认预设
+pub fn find_preset<'a>(presets: &'a [JvmPreset], name: &str) -> Option<&'a JvmPreset> {
+    presets
+        .iter()
+        .find(|preset| preset.name == name)
+        .or_else(|| presets.iter().find(|preset| preset.name == DEFAULT_PRESET))
+}
+
+const MIB: u64 = 1024 * 1024;
+/// 自动分配时的最小堆内存（MiB）
+const MIN_AUTO_XMX: u64 = 1024;
+/// 自动分配时的最大堆内存（MiB），更大的堆会使GC停顿变长
+const MAX_AUTO_XMX: u64 = 12288;
+
+/// 内存设置错误
+pub enum MemoryError {
+    /// 无法解析的值
+    Invalid(String),
+    /// 最小内存大于最大内存
+    XmsGreaterThanXmx,
+    /// 最大内存超过物理内存，物理内存（MiB）
+    ExceedsTotal(u64),
+}
+
+impl std::fmt::Display for MemoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MemoryError::Invalid(s) => write!(
+                f,
+                "\"{s}\" is not a valid memory size, please use a number followed by K, M or G, like 2G."
+            ),
+            MemoryError::XmsGreaterThanXmx => {
+                write!(f, "The minimum memory is larger than the maximum memory.")
+            }
+            MemoryError::ExceedsTotal(total) => write!(
+                f,
+                "The maximum memory is larger than the physical memory ({total} MiB)."
+            ),
+        }
+    }
+}
+
+/// 解析-Xms/-Xmx的值，返回字节数
+/// 与JVM一致，支持k、m、g、t后缀，不区分大小写，无后缀时单位为字节
+pub fn parse_memory(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let (number, unit) = match s.chars().last()?.to_ascii_lowercase() {
+        'k' => (&s[..s.len() - 1], 1024),
+        'm' => (&s[..s.len() - 1], MIB),
+        'g' => (&s[..s.len() - 1], 1024 * MIB),
+        't' => (&s[..s.len() - 1], 1024 * 1024 * MIB),
+        _ => (s, 1),
+    };
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    number.parse::<u64>().ok()?.checked_mul(unit)
+}
+
+/// 检查内存设置，total: 物理内存（字节），无法获取时为None
+pub fn check_memory(xms: &str, xmx: &str, total: Option<u64>) -> Result<(), MemoryError> {
+    let xms_bytes = parse_memory(xms).ok_or(MemoryError::Invalid(xms.to_string()))?;
+    let xmx_bytes = parse_memory(xmx).ok_or(MemoryError::Invalid(xmx.to_string()))?;
+    if xms_bytes > xmx_bytes {
+        return Err(MemoryError::XmsGreaterThanXmx);
+    }
+    if let Some(total) = total
+        && xmx_bytes > total
+    {
+        return Err(MemoryError::ExceedsTotal(total / MIB));
+    }
+    Ok(())
+}
+
+/// 根据系统内存、mod数量和是否安装mod加载器计算内存，返回(xms, xmx)
+/// total, available: 物理内存和可用内存（字节）
+pub fn auto_memory(total: u64, available: u64, mods: usize, has_loader: bool) -> (String, String) {
+    let (total, available) = (total / MIB, available / MIB);
+
+    // 原版2G足够，mod加载器及每个mod需要额外的内存
+    let wanted = if has_loader {
+        3072 + 64 * mods as u64
+    } else {
+        2048
+    };
+    // 为系统保留至少1/4且不少于2G，并尽量不超过当前可用内存
+    let limit = total
+        .saturating_sub((total / 4).max(2048))
+        .min(available.saturating_sub(512));
+    let xmx = (wanted.min(MAX_AUTO_XMX).min(limit) / 256 * 256).max(MIN_AUTO_XMX);
+    let xms = ((xmx / 2).min(2048) / 256 * 256).max(256);
+
+    (format!("{xms}M"), format!("{xmx}M"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GIB: u64 = 1024 * MIB;
+
+    #[test]
+    fn parse_memory_units() {
+        assert_eq!(parse_memory("512"), Some(512));
+        assert_eq!(parse_memory("1k"), Some(1024));
+        assert_eq!(parse_memory("2M"), Some(2 * MIB));
+        assert_eq!(parse_memory("4g"), Some(4 * GIB));
+        assert_eq!(parse_memory(" 2G "), Some(2 * GIB));
+        assert_eq!(parse_memory("1T"), Some(1024 * GIB));
+        for invalid in ["", "G", "1.5G", "-1G", "2GB", "20000000T"] {
+            assert_eq!(parse_memory(invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn check_memory_errors() {
+        assert!(check_memory("1G", "4G", Some(16 * GIB)).is_ok());
+        assert!(check_memory("4G", "4G", None).is_ok());
+        assert!(matches!(
+            check_memory("1G", "4GB", None),
+            Err(MemoryError::Invalid(s)) if s == "4GB"
+        ));
+        assert!(matches!(
+            check_memory("4G", "2G", None),
+            Err(MemoryError::XmsGreaterThanXmx)
+        ));
+        assert!(matches!(
+            check_memory("1G", "32G", Some(16 * GIB)),
+            Err(MemoryError::ExceedsTotal(16384))
+        ));
+    }
+
+    #[test]
+    fn auto_memory_by_mods() {
+        // 原版
+        assert_eq!(
+            auto_memory(16 * GIB, 12 * GIB, 0, false),
+            ("1024M".into(), "2048M".into())
+        );
+        // mod加载器，每个mod增加64M，向下取整到256M
+        assert_eq!(
+            auto_memory(16 * GIB, 12 * GIB, 0, true),
+            ("1536M".into(), "3072M".into())
+        );
+        assert_eq!(
+            auto_memory(16 * GIB, 12 * GIB, 50, true),
+            ("2048M".into(), "6144M".into())
+        );
+        // 不超过上限
+        assert_eq!(
+            auto_memory(32 * GIB, 30 * GIB, 300, true),
+            ("2048M".into(), "12288M".into())
+        );
+    }
+
+    #[test]
+    fn auto_memory_by_system() {
+        // 为系统保留至少2G
+        assert_eq!(
+            auto_memory(4 * GIB, 3 * GIB, 0, true),
+            ("1024M".into(), "2048M".into())
+        );
+        // 不超过可用内存
+        assert_eq!(
+            auto_memory(16 * GIB, 2 * GIB, 50, true),
+            ("768M".into(), "1536M".into())
+        );
+        // 内存不足时使用下限
+        assert_eq!(
+            auto_memory(2 * GIB, GIB, 0, false),
+            ("512M".into(), "1024M".into())
+        );
+    }
+}
@@ -1,8 +1,9 @@
 //! 启动相关
 //! mc::launch 获取MC的启动参数
 
-use log::error;
+use log::{error, info};
 use serde_json::Value;
+use std::collections::HashMap;
 use std::env::consts as env;
 use std::fs::{self, exists, read_to_string};
 
@@ -10,7 +11,10 @@ use utils::{check_rules, download};
 
 use crate::MCInstallation;
 use crate::account::Account;
-use crate::download::{DownloadError, DownloadTask, download_assets, download_libraries};
+use crate::download::{
+    DownloadError, DownloadTask, download_assets, download_libraries, download_log_config,
+    get_game_assets, natives_dir, remove_old_natives,
+};
 use crate::launch::LaunchError::{DeserializeError, IOError};
 
 pub enum LaunchError {
@@ -53,6 +57,13 @@ impl From<utils::DLError> for LaunchError {
     }
 }
 
+/// 与预设无关、总是添加的JVM参数
+const BASE_JVM_ARGS: [&str; 3] = [
+    "-Dfml.ignoreInvalidMinecraftCertificates=True",
+    "-Dfml.ignorePatchDiscrepancies=True",
+    "-Dlog4j2.formatMsgNoLookups=true",
+];
+
 /// 从json对象单次获取参数
 fn add_arg(n: &Value) -> Result<Vec<String>, LaunchError> {
     let mut result: Vec<String> = Vec::new();
@@ -86,14 +97,7 @@ fn add_arg(n: &Value) -> Result<Vec<String>, LaunchError> {
 /// 获取MC和JVM参数（1.13+）
 fn get_args_new(n: &Value) -> Result<(Vec<String>, Vec<String>), LaunchError> {
     let mut game_args: Vec<String> = Vec::new();
-    let mut jvm_args: Vec<String> = vec![
-        "-XX:+UseG1GC".to_string(),
-        "-XX:-UseAdaptiveSizePolicy".to_string(),
-        "-XX:-OmitStackTraceInFastThrow".to_string(),
-        "-Dfml.ignoreInvalidMinecraftCertificates=True".to_string(),
-        "-Dfml.ignorePatchDiscrepancies=True".to_string(),
-        "-Dlog4j2.formatMsgNoLookups=true".to_string(),
-    ];
+    let mut jvm_args: Vec<String> = Vec::new();
 
     game_args.append(&mut add_arg(&n["arguments"]["game"])?);
     if !n["arguments"]["jvm"].is_null() {
@@ -107,14 +111,7 @@ fn get_args_new(n: &Value) -> Result<(Vec<String>, Vec<String>), LaunchError> {
 /// 获取MC和JVM参数（1.13-）
 fn get_args_old(n: &Value) -> Result<(Vec<String>, Vec<String>), LaunchError> {
     let mut game_args: Vec<String> = Vec::new();
-    let mut jvm_args: Vec<String> = vec![
-        "-XX:+UseG1GC".to_string(),
-        "-XX:-UseAdaptiveSizePolicy".to_string(),
-        "-XX:-OmitStackTraceInFastThrow".to_string(),
-        "-Dfml.ignoreInvalidMinecraftCertificates=True".to_string(),
-        "-Dfml.ignorePatchDiscrepancies=True".to_string(),
-        "-Dlog4j2.formatMsgNoLookups=true".to_string(),
-    ];
+    let mut jvm_args: Vec<String> = Vec::new();
 
     let args: Vec<&str> = n["minecraftArguments"]
         .as_str()
@@ -136,14 +133,7 @@ fn get_args_old(n: &Value) -> Result<(Vec<String>, Vec<String>), LaunchError> {
 /// 获取MC和JVM参数（原版）
 fn get_args(n: &Value) -> Result<(Vec<String>, Vec<String>), LaunchError> {
     let mut game_args: Vec<String> = Vec::new();
-    let mut jvm_args: Vec<String> = vec![
-        "-XX:+UseG1GC".to_string(),
-        "-XX:-UseAdaptiveSizePolicy".to_string(),
-        "-XX:-OmitStackTraceInFastThrow".to_string(),
-        "-Dfml.ignoreInvalidMinecraftCertificates=True".to_string(),
-        "-Dfml.ignorePatchDiscrepancies=True".to_string(),
-        "-Dlog4j2.formatMsgNoLookups=true".to_string(),
-    ];
+    let mut jvm_args: Vec<String> = Vec::new();
 
     if !n["arguments"].is_null() {
         // MC版本 >= 1.13
@@ -169,6 +159,58 @@ fn get_args(n: &Value) -> Result<(Vec<String>, Vec<String>), LaunchError> {
     Ok((game_args, jvm_args))
 }
 
+/// 获取library的唯一标识 group:artifact[:classifier][@ext]，与版本号无关
+/// 1.14~1.18的natives与本体同名，另以natives区分，否则natives会被当作重复项去掉
+fn library_key(item: &Value) -> Option<String> {
+    let name = item["name"].as_str()?;
+    let (name, ext) = match name.split_once('@') {
+        Some((name, ext)) => (name, Some(ext)),
+        None => (name, None),
+    };
+
+    let split: Vec<&str> = name.split(":").collect();
+    if split.len() < 3 {
+        return None;
+    }
+
+    let mut key = format!("{}:{}", split[0], split[1]);
+    if let Some(classifier) = split.get(3) {
+        key = key + ":" + classifier;
+    }
+    if let Some(ext) = ext {
+        key = key + "@" + ext;
+    }
+    if item["natives"].is_object() || item["downloads"]["classifiers"].is_object() {
+        key += " natives";
+    }
+    Some(key)
+}
+
+/// libraries去重，同一group:artifact[:classifier]只保留第一个（子版本在前，与官启一致）
+/// natives与本体分别去重
+pub(crate) fn dedup_libraries(n: &Value) -> Result<Value, LaunchError> {
+    let mut kept: HashMap<String, String> = HashMap::new();
+    let mut result: Vec<Value> = Vec::new();
+    for item in n.as_array().ok_or(LaunchError::DataInvalid)? {
+        // 先判断rules，避免被不适用于当前系统的library顶替
+        if !item["rules"].is_null() && !check_rules(&item["rules"]) {
+            continue;
+        }
+
+        let name = item["name"].as_str().ok_or(LaunchError::DataInvalid)?;
+        if let Some(key) = library_key(item) {
+            if let Some(kept_name) = kept.get(&key) {
+                info!("Drop duplicated library {name}, {kept_name} is used instead.");
+                continue;
+            }
+            kept.insert(key, name.to_string());
+        }
+        result.push(item.clone());
+    }
+
+    Ok(Value::Array(result))
+}
+
 /// 获取-cp参数
 fn get_classpaths(n: &Value, game_path: &str) -> Result<Vec<String>, LaunchError> {
     let mut result: Vec<String> = Vec::new();
@@ -216,12 +258,16 @@ fn get_classpaths(n: &Value, game_path: &str) -> Result<Vec<String>, LaunchError
 }
 
 /// 获取启动总命令，返回参数和下载列表
+/// launcher_log_config: 使用启动器提供的日志配置（每行一条XML事件，由mc::log_event解析）代替官方配置
+/// jvm_flags: 由mc::jvm预设得到的JVM参数，位于用户参数之前
 /// Note that all the download sources should be replaced
 /// {assets_source}, {fabric_source}, {game_source}, {libraries_source}
 pub async fn get_launch_command(
     account: &Account,
     game: &MCInstallation,
     game_path: &str,
+    launcher_log_config: bool,
+    jvm_flags: Vec<String>,
 ) -> Result<(Vec<String>, Vec<DownloadTask>), LaunchError> {
     let mut result: Vec<String> = Vec::new();
     let dir = game_path.to_string() + "/versions/" + game.version.as_str(); // 游戏目录
@@ -237,8 +283,11 @@ pub async fn get_launch_command(
 
     // mod需要额外写入的参数
     let mut game_args: Vec<String> = game.game_args.clone();
-    let mut jvm_args: Vec<String> = game.jvm_args.clone();
+    let mut jvm_args: Vec<String> = jvm_flags;
+    jvm_args.extend(BASE_JVM_ARGS.iter().map(|arg| arg.to_string()));
+    jvm_args.extend(game.jvm_args.iter().cloned());
     let mut libraries_json = json["libraries"].clone();
+    let mut logging_json = json["logging"]["client"].clone();
 
     // 判断inheritsFrom（mod需要）
     if json["inheritsFrom"].is_string() {
@@ -271,6 +320,9 @@ pub async fn get_launch_command(
                 .as_str()
                 .ok_or(LaunchError::DataInvalid)?
                 .to_string();
+            if logging_json.is_null() {
+                logging_json = parent["logging"]["client"].clone();
+            }
 
             // MC和JVM的参数
             if !parent["arguments"].is_null() {
@@ -310,6 +362,9 @@ pub async fn get_launch_command(
         jvm_args.append(&mut temp_jvm_args);
     }
 
+    // 按artifact去重，避免不同版本的同一library同时出现在classpath中
+    let libraries_json = dedup_libraries(&libraries_json)?;
+
     // classpaths列表
     let mut classpaths: Vec<String> = Vec::new();
     classpaths.append(&mut get_classpaths(&libraries_json, game_path)?);
@@ -333,6 +388,17 @@ pub async fn get_launch_command(
         "-Xms".to_string() + game.xms.as_str(),
         "-Xmx".to_string() + game.xmx.as_str(),
     ]);
+
+    // 日志配置
+    let (log_arg, log_task) = download_log_config(
+        &logging_json,
+        game_path,
+        "{game_source}",
+        launcher_log_config,
+    )?;
+    if let Some(arg) = log_arg {
+        jvm_args.push(arg);
+    }
     game_args.append(&mut vec![
         "--height".into(),
         game.height.to_string(),
@@ -352,9 +418,20 @@ pub async fn get_launch_command(
     result.append(&mut game_args);
 
     // 版本隔离
-    let game_dir = if game.separated { &dir } else { game_path };
+    let game_dir = &game.get_game_dir(game_path);
+
+    // asset index，旧版本的${game_assets}需要根据其确定
+    let index_dir = game_path.to_string() + "/assets/indexes/";
+    let index_path = index_dir.clone() + &asset_index + ".json";
+    if !exists(&index_path)? {
+        if !exists(&index_dir)? {
+            fs::create_dir_all(&index_dir)?;
+        }
+        download(asset_index_url.clone(), index_path, 3).await?;
+    }
+    let game_assets = get_game_assets(game_path, &asset_index, game_dir)?;
+    let natives_dir = natives_dir(&libraries_json, &dir)?;
 
-    let os = if env::OS == "macOS" { "osx" } else { env::OS };
     // 替换模板
     for item in result.iter_mut() {
         *item = item
@@ -366,7 +443,7 @@ pub async fn get_launch_command(
             // .replace("${authlib_injector_param}", "") // 暂不支持
             .replace("${classpath}", &cp)
             .replace("${classpath_separator}", ":")
-            .replace("${game_assets}", &(game_path.to_string() + "/assets")) // support old versions
+            .replace("${game_assets}", &game_assets) // support old versions
             .replace("${game_directory}", &game_dir)
             .replace("${launcher_name}", "\"CE Minecraft Launcher\"")
             .replace("${launcher_version}", env!("CARGO_PKG_VERSION"))
@@ -374,10 +451,7 @@ pub async fn get_launch_command(
                 "${library_directory}",
                 &(game_path.to_string() + "/libraries"),
             )
-            .replace(
-                "${natives_directory}",
-                &(dir.clone() + "/natives-" + os + "-" + env::ARCH),
-            )
+            .replace("${natives_directory}", &natives_dir)
             .replace("${user_properties}", "{}")
             .replace("${user_type}", &String::from(account.account_type.clone()))
             .replace("${version_name}", &game.version)
@@ -394,17 +468,8 @@ pub async fn get_launch_command(
             .replace("https://piston-meta.mojang.com", "{game_source}");
         tasks.push(DownloadTask::new(url, jar_path, None));
     }
-
-    // 处理依赖
-
-    // json first
-    let index_dir = game_path.to_string() + "/assets/indexes/";
-    let index_path = index_dir.clone() + &asset_index + ".json";
-    if !exists(&index_path)? {
-        if !exists(&index_dir)? {
-            fs::create_dir_all(&index_dir)?;
-        }
-        download(asset_index_url.clone(), index_path, 3).await?;
+    if let Some(task) = log_task {
+        tasks.push(task);
     }
 
     // assets
@@ -412,16 +477,156 @@ pub async fn get_launch_command(
         game_path,
         &asset_index,
         "{assets_source}",
+        game_dir,
     )?);
 
     // download libraries
     tasks.append(&mut download_libraries(
         &libraries_json,
         game_path,
-        &dir,
+        &natives_dir,
         "{libraries_source}",
         "{fabric_source}",
     )?);
 
     Ok((result, tasks))
 }
+
+/// 下载完成后检查日志配置，文件缺失或因校验失败被删除时去掉其参数，避免游戏找不到配置文件
+pub fn check_log_config(args: &mut Vec<String>) {
+    args.retain(|arg| match arg.strip_prefix("-Dlog4j.configurationFile=") {
+        Some(path) if !std::path::Path::new(path).is_file() => {
+            error!("{path} is missing, the default logging config is used.");
+            false
+        }
+        _ => true,
+    });
+}
+
+/// 删除旧的natives文件夹，args: get_launch_command()返回的命令
+/// 该版本有游戏正在运行时不能调用，其natives文件夹可能是旧的
+pub fn clean_natives(args: &[String]) {
+    let Some(natives_dir) = args
+        .iter()
+        .find_map(|arg| arg.strip_prefix("-Djava.library.path="))
+    else {
+        return;
+    };
+    if let Err(e) = remove_old_natives(natives_dir) {
+        error!("Failed to remove old natives: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn names(libraries: &Value) -> Vec<&str> {
+        libraries
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["name"].as_str().unwrap())
+            .collect()
+    }
+
+    fn dedup(libraries: &Value) -> Value {
+        match dedup_libraries(libraries) {
+            Ok(result) => result,
+            Err(_) => panic!("failed to deduplicate libraries"),
+        }
+    }
+
+    /// 1.16.5的libraries中，lwjgl的本体和natives同名
+    #[test]
+    fn keep_natives_of_same_name() {
+        let libraries = json!([
+            {
+                "downloads": {
+                    "artifact": {
+                        "path": "org/lwjgl/lwjgl/3.2.2/lwjgl-3.2.2.jar",
+                        "url": "https://libraries.minecraft.net/org/lwjgl/lwjgl/3.2.2/lwjgl-3.2.2.jar"
+                    }
+                },
+                "name": "org.lwjgl:lwjgl:3.2.2"
+            },
+            {
+                "downloads": {
+                    "artifact": {
+                        "path": "org/lwjgl/lwjgl-glfw/3.2.2/lwjgl-glfw-3.2.2.jar",
+                        "url": "https://libraries.minecraft.net/org/lwjgl/lwjgl-glfw/3.2.2/lwjgl-glfw-3.2.2.jar"
+                    }
+                },
+                "name": "org.lwjgl:lwjgl-glfw:3.2.2"
+            },
+            {
+                "downloads": {
+                    "artifact": {
+                        "path": "org/lwjgl/lwjgl/3.2.2/lwjgl-3.2.2.jar",
+                        "url": "https://libraries.minecraft.net/org/lwjgl/lwjgl/3.2.2/lwjgl-3.2.2.jar"
+                    },
+                    "classifiers": {
+                        "natives-linux": {
+                            "path": "org/lwjgl/lwjgl/3.2.2/lwjgl-3.2.2-natives-linux.jar",
+                            "url": "https://libraries.minecraft.net/org/lwjgl/lwjgl/3.2.2/lwjgl-3.2.2-natives-linux.jar"
+                        },
+                        "natives-macos": {
+                            "path": "org/lwjgl/lwjgl/3.2.2/lwjgl-3.2.2-natives-macos.jar",
+                            "url": "https://libraries.minecraft.net/org/lwjgl/lwjgl/3.2.2/lwjgl-3.2.2-natives-macos.jar"
+                        },
+                        "natives-windows": {
+                            "path": "org/lwjgl/lwjgl/3.2.2/lwjgl-3.2.2-natives-windows.jar",
+                            "url": "https://libraries.minecraft.net/org/lwjgl/lwjgl/3.2.2/lwjgl-3.2.2-natives-windows.jar"
+                        }
+                    }
+                },
+                "name": "org.lwjgl:lwjgl:3.2.2",
+                "natives": {
+                    "linux": "natives-linux",
+                    "osx": "natives-macos",
+                    "windows": "natives-windows"
+                }
+            }
+        ]);
+
+        let result = dedup(&libraries);
+        assert_eq!(
+            names(&result),
+            vec![
+                "org.lwjgl:lwjgl:3.2.2",
+                "org.lwjgl:lwjgl-glfw:3.2.2",
+                "org.lwjgl:lwjgl:3.2.2"
+            ]
+        );
+        assert!(result[2]["natives"].is_object());
+    }
+
+    /// 子版本的libraries在前，覆盖父版本中的旧版本
+    #[test]
+    fn child_overrides_parent() {
+        let libraries = json!([
+            { "name": "org.apache.logging.log4j:log4j-api:2.15.0" },
+            { "name": "net.minecraftforge:forge:1.16.5-36.2.39:universal" },
+            { "name": "org.apache.logging.log4j:log4j-api:2.8.1" },
+            { "name": "com.mojang:patchy:1.3.9" },
+            { "name": "org.lwjgl:lwjgl:3.3.1" },
+            { "name": "org.lwjgl:lwjgl:3.3.1", "natives": { "linux": "natives-linux" } },
+            { "name": "org.lwjgl:lwjgl:3.2.2" },
+            { "name": "org.lwjgl:lwjgl:3.2.2", "natives": { "linux": "natives-linux" } }
+        ]);
+
+        let result = dedup(&libraries);
+        assert_eq!(
+            names(&result),
+            vec![
+                "org.apache.logging.log4j:log4j-api:2.15.0",
+                "net.minecraftforge:forge:1.16.5-36.2.39:universal",
+                "com.mojang:patchy:1.3.9",
+                "org.lwjgl:lwjgl:3.3.1",
+                "org.lwjgl:lwjgl:3.3.1"
+            ]
+        );
+        assert!(result[4]["natives"].is_object());
+    }
+}
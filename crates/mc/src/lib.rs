@@ -1,8 +1,13 @@
 pub mod account;
+pub mod crash;
 mod download;
+pub mod forge;
+pub mod jvm;
 pub mod launch;
+pub mod log_event;
+pub mod repair;
 mod version;
 
-pub use download::{DownloadError, manifest};
+pub use download::{DownloadError, DownloadTask, manifest};
 pub use version::MCInstallation;
 pub use version::MCType;
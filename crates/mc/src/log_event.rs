@@ -0,0 +1,218 @@
+//! 游戏日志事件解析
+//! mc::log_event 将log4j输出的XML事件转换为文本行
+//! 官方日志配置使用<log4j:Event>格式，启动器提供的配置使用log4j2的<Event>格式
+
+use std::fmt;
+
+/// 一条日志事件
+pub struct LogEvent {
+    pub level: String,
+    pub logger: String,
+    pub thread: String,
+    pub message: String,
+    /// 异常及其调用栈
+    pub throwable: Option<String>,
+}
+
+impl fmt::Display for LogEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}/{}] [{}]: {}",
+            self.thread, self.level, self.logger, self.message
+        )?;
+        if let Some(throwable) = &self.throwable {
+            write!(f, "\n{throwable}")?;
+        }
+        Ok(())
+    }
+}
+
+/// 事件的开始和结束标签
+const EVENT_TAGS: [(&str, &str); 2] =
+    [("<log4j:Event ", "</log4j:Event>"), ("<Event ", "</Event>")];
+
+/// 还原XML转义的字符
+fn unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#10;", "\n")
+        .replace("&#13;", "\r")
+        .replace("&amp;", "&")
+}
+
+/// 读取开始标签中的属性
+fn attr(tag: &str, name: &str) -> Option<String> {
+    let pattern = format!(" {name}=\"");
+    let start = tag.find(&pattern)? + pattern.len();
+    let end = start + tag[start..].find('"')?;
+    Some(unescape(&tag[start..end]))
+}
+
+/// 读取元素的文本，可以为CDATA
+fn text(xml: &str, name: &str) -> Option<String> {
+    let (open, close) = (format!("<{name}>"), format!("</{name}>"));
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find(&close)?;
+    let content = &xml[start..end];
+    Some(match content.trim().strip_prefix("<![CDATA[") {
+        Some(data) => data.strip_suffix("]]>").unwrap_or(data).to_string(),
+        None => unescape(content),
+    })
+}
+
+/// 解析一条完整的事件，不是日志事件时返回None
+pub fn parse_event(xml: &str) -> Option<LogEvent> {
+    let xml = xml.trim();
+    let tag = &xml[..xml.find('>')?];
+    if tag.starts_with("<log4j:Event ") {
+        Some(LogEvent {
+            level: attr(tag, "level")?,
+            logger: attr(tag, "logger").unwrap_or_default(),
+            thread: attr(tag, "thread").unwrap_or_default(),
+            message: text(xml, "log4j:Message").unwrap_or_default(),
+            throwable: text(xml, "log4j:Throwable"),
+        })
+    } else if tag.starts_with("<Event ") {
+        // log4j2的异常为<Thrown>元素，只取类名和信息
+        let throwable = xml.find("<Thrown ").and_then(|start| {
+            let thrown = &xml[start..];
+            let thrown = &thrown[..thrown.find('>')?];
+            let name = attr(thrown, "name")?;
+            Some(match attr(thrown, "message") {
+                Some(message) => format!("{name}: {message}"),
+                None => name,
+            })
+        });
+        Some(LogEvent {
+            level: attr(tag, "level")?,
+            logger: attr(tag, "loggerName").unwrap_or_default(),
+            thread: attr(tag, "thread").unwrap_or_default(),
+            message: text(xml, "Message").unwrap_or_default(),
+            throwable,
+        })
+    } else {
+        None
+    }
+}
+
+/// 逐行读取输出，合并跨行的事件
+#[derive(Default)]
+pub struct EventReader {
+    /// 未结束的事件
+    pending: String,
+    /// 未结束事件的结束标签
+    end_tag: Option<&'static str>,
+}
+
+impl EventReader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 读取一行，返回转换后的文本，事件未结束时返回None
+    pub fn feed(&mut self, line: &str) -> Option<String> {
+        let end_tag = match self.end_tag {
+            Some(end_tag) => end_tag,
+            None => {
+                let trimmed = line.trim_start();
+                match EVENT_TAGS
+                    .iter()
+                    .find(|(start, _)| trimmed.starts_with(start))
+                {
+                    Some((_, end_tag)) => *end_tag,
+                    // 不是事件的输出原样保留
+                    None => return Some(line.to_string()),
+                }
+            }
+        };
+        if !self.pending.is_empty() {
+            self.pending.push('\n');
+        }
+        self.pending.push_str(line);
+        if !line.contains(end_tag) {
+            self.end_tag = Some(end_tag);
+            return None;
+        }
+        self.end_tag = None;
+        let xml = std::mem::take(&mut self.pending);
+        Some(match parse_event(&xml) {
+            Some(event) => event.to_string(),
+            None => xml,
+        })
+    }
+
+    /// 输出结束时取出未结束的事件
+    pub fn flush(&mut self) -> Option<String> {
+        self.end_tag = None;
+        if self.pending.is_empty() {
+            None
+        } else {
+            Some(std::mem::take(&mut self.pending))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_event() {
+        let mut reader = EventReader::new();
+        let lines = [
+            r#"<log4j:Event logger="net.minecraft.client.Minecraft" timestamp="1700000000000" level="INFO" thread="Render thread">"#,
+            "  <log4j:Message><![CDATA[Setting user: Steve]]></log4j:Message>",
+            "</log4j:Event>",
+        ];
+        assert_eq!(reader.feed(lines[0]), None);
+        assert_eq!(reader.feed(lines[1]), None);
+        assert_eq!(
+            reader.feed(lines[2]).as_deref(),
+            Some("[Render thread/INFO] [net.minecraft.client.Minecraft]: Setting user: Steve")
+        );
+    }
+
+    #[test]
+    fn legacy_throwable() {
+        let xml = r#"<log4j:Event logger="Foo" timestamp="1" level="ERROR" thread="main">
+<log4j:Message><![CDATA[Failed]]></log4j:Message>
+<log4j:Throwable><![CDATA[java.lang.RuntimeException: boom
+	at Foo.bar(Foo.java:1)]]></log4j:Throwable>
+</log4j:Event>"#;
+        let event = parse_event(xml).map(|event| event.to_string());
+        assert_eq!(
+            event.as_deref(),
+            Some(
+                "[main/ERROR] [Foo]: Failed\njava.lang.RuntimeException: boom\n\tat Foo.bar(Foo.java:1)"
+            )
+        );
+    }
+
+    #[test]
+    fn log4j2_event() {
+        let mut reader = EventReader::new();
+        let line = r#"<Event xmlns="http://logging.apache.org/log4j/2.0/events" timeMillis="1" thread="Worker-1" level="WARN" loggerName="net.minecraft.Util" endOfBatch="false"><Message>a &lt;b&gt; &amp; c</Message><Thrown commonElementCount="0" message="bad &quot;x&quot;" name="java.io.IOException"/></Event>"#;
+        assert_eq!(
+            reader.feed(line).as_deref(),
+            Some("[Worker-1/WARN] [net.minecraft.Util]: a <b> & c\njava.io.IOException: bad \"x\"")
+        );
+    }
+
+    #[test]
+    fn plain_lines() {
+        let mut reader = EventReader::new();
+        assert_eq!(
+            reader.feed("Exception in thread").as_deref(),
+            Some("Exception in thread")
+        );
+        assert_eq!(reader.feed(r#"<log4j:Event level="INFO">"#), None);
+        assert_eq!(
+            reader.flush().as_deref(),
+            Some(r#"<log4j:Event level="INFO">"#)
+        );
+        assert_eq!(reader.flush(), None);
+    }
+}
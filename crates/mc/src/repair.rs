@@ -0,0 +1,252 @@
+//! 游戏文件校验及修复
+//! mc::repair 检查本体、libraries、natives和assets，删除损坏的文件并重新下载
+
+use log::{info, warn};
+use serde_json::Value;
+use std::env::consts as env;
+use std::fs::{create_dir_all, exists, metadata, read_to_string, remove_dir_all, remove_file};
+
+use utils::{check_rules, check_sha1, download};
+
+use crate::MCInstallation;
+use crate::download::{
+    DownloadTask, FABRIC_MAVEN, download_assets, download_libraries, maven_path, natives_dir,
+};
+use crate::launch::{LaunchError, dedup_libraries};
+
+/// 报告中每类最多列出的文件数
+const REPORT_FILES: usize = 10;
+
+/// 校验结果
+#[derive(Default)]
+pub struct RepairReport {
+    /// 校验的文件数
+    pub checked: usize,
+    /// 缺失的文件
+    pub missing: Vec<String>,
+    /// 大小或sha1不匹配，已删除的文件
+    pub corrupted: Vec<String>,
+}
+
+impl std::fmt::Display for RepairReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Checked {} files, {} missing, {} corrupted.",
+            self.checked,
+            self.missing.len(),
+            self.corrupted.len()
+        )?;
+        for (title, list) in [("Corrupted", &self.corrupted), ("Missing", &self.missing)] {
+            if list.is_empty() {
+                continue;
+            }
+            write!(f, "\n{title}:")?;
+            for file in list.iter().take(REPORT_FILES) {
+                write!(f, "\n{file}")?;
+            }
+            if list.len() > REPORT_FILES {
+                write!(f, "\n... and {} more", list.len() - REPORT_FILES)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 应有的文件
+struct ExpectedFile {
+    path: String,
+    sha1: Option<String>,
+    size: Option<u64>,
+}
+
+impl ExpectedFile {
+    /// node: 含sha1和size的json对象，如downloads.artifact
+    fn new(path: String, node: &Value) -> Self {
+        Self {
+            path,
+            sha1: node["sha1"].as_str().map(|s| s.to_string()),
+            size: node["size"].as_u64(),
+        }
+    }
+}
+
+impl RepairReport {
+    /// 检查文件，损坏时删除
+    fn check(&mut self, file: &ExpectedFile) -> Result<(), LaunchError> {
+        self.checked += 1;
+        if !exists(&file.path)? {
+            self.missing.push(file.path.clone());
+            return Ok(());
+        }
+
+        // 先比较大小，避免无谓地计算sha1
+        let mut valid = match file.size {
+            Some(size) => metadata(&file.path)?.len() == size,
+            None => true,
+        };
+        if valid && let Some(sha1) = &file.sha1 {
+            valid = check_sha1(&file.path, sha1)?;
+        }
+        if !valid {
+            warn!("{} is corrupted.", file.path);
+            remove_file(&file.path)?;
+            self.corrupted.push(file.path.clone());
+        }
+        Ok(())
+    }
+}
+
+/// 获取libraries中适用于当前系统的文件
+fn library_files(libraries: &Value, lib_dir: &str) -> Result<Vec<ExpectedFile>, LaunchError> {
+    let os = if env::OS == "macOS" { "osx" } else { env::OS };
+    let mut files = Vec::new();
+    for node in libraries.as_array().ok_or(LaunchError::DataInvalid)? {
+        if node["rules"].is_array() && !check_rules(&node["rules"]) {
+            continue;
+        }
+
+        // 旧版本的natives
+        if node["natives"][os].is_string() && node["downloads"]["classifiers"].is_object() {
+            let arch = if env::ARCH.contains("64") { "64" } else { "32" };
+            let key = node["natives"][os]
+                .as_str()
+                .ok_or(LaunchError::DataInvalid)?
+                .replace("${arch}", arch);
+            let node = &node["downloads"]["classifiers"][&key];
+            let path = node["path"].as_str().ok_or(LaunchError::DataInvalid)?;
+            files.push(ExpectedFile::new(lib_dir.to_string() + "/" + path, node));
+        }
+
+        if node["downloads"]["artifact"].is_object() {
+            let node = &node["downloads"]["artifact"];
+            let path = node["path"].as_str().ok_or(LaunchError::DataInvalid)?;
+            files.push(ExpectedFile::new(lib_dir.to_string() + "/" + path, node));
+        } else if node["url"].as_str() == Some(FABRIC_MAVEN) {
+            // Fabric的sha1和size直接位于library中，旧版本没有
+            let name = node["name"].as_str().ok_or(LaunchError::DataInvalid)?;
+            let path = maven_path(name).ok_or(LaunchError::DataInvalid)?;
+            files.push(ExpectedFile::new(lib_dir.to_string() + "/" + &path, node));
+        }
+    }
+    Ok(files)
+}
+
+/// 校验并修复游戏文件，删除损坏的文件和natives，返回校验结果和需要下载的文件
+/// 与get_launch_command相同，下载源需要替换
+/// {assets_source}, {fabric_source}, {game_source}, {libraries_source}
+pub async fn repair(
+    game: &MCInstallation,
+    game_path: &str,
+) -> Result<(RepairReport, Vec<DownloadTask>), LaunchError> {
+    let mut report = RepairReport::default();
+    let mut tasks = Vec::new();
+    let dir = game_path.to_string() + "/versions/" + &game.version;
+    let json = serde_json::from_str::<Value>(&read_to_string(
+        dir.clone() + "/" + &game.version + ".json",
+    )?)?;
+
+    // 有mod loader时，本体、assets和其余libraries来自继承的版本
+    let mut libraries = json["libraries"].clone();
+    let parent = match json["inheritsFrom"].as_str() {
+        Some(parent_version) => {
+            let parent_json_path = game_path.to_string()
+                + "/versions/"
+                + parent_version
+                + "/"
+                + parent_version
+                + ".json";
+            let parent = serde_json::from_str::<Value>(&read_to_string(&parent_json_path)?)?;
+            libraries
+                .as_array_mut()
+                .ok_or(LaunchError::DataInvalid)?
+                .extend(
+                    parent["libraries"]
+                        .as_array()
+                        .ok_or(LaunchError::DataInvalid)?
+                        .iter()
+                        .cloned(),
+                );
+            parent
+        }
+        None => json,
+    };
+    let libraries = dedup_libraries(&libraries)?;
+
+    // 本体
+    let client = &parent["downloads"]["client"];
+    let jar_path = dir.clone() + "/" + &game.version + ".jar";
+    report.check(&ExpectedFile::new(jar_path.clone(), client))?;
+    if !exists(&jar_path)? {
+        let url = client["url"]
+            .as_str()
+            .ok_or(LaunchError::DataInvalid)?
+            .replace("https://piston-meta.mojang.com", "{game_source}");
+        tasks.push(DownloadTask::new(url, jar_path, None));
+    }
+
+    // libraries
+    let lib_dir = game_path.to_string() + "/libraries";
+    for file in library_files(&libraries, &lib_dir)? {
+        report.check(&file)?;
+    }
+
+    // 删除natives，下载libraries时会重新解压
+    let natives_dir = natives_dir(&libraries, &dir)?;
+    if exists(&natives_dir)? {
+        info!("Remove {natives_dir}.");
+        remove_dir_all(&natives_dir)?;
+    }
+    tasks.append(&mut download_libraries(
+        &libraries,
+        game_path,
+        &natives_dir,
+        "{libraries_source}",
+        "{fabric_source}",
+    )?);
+
+    // asset index，需要先下载才能获取assets列表
+    let asset_index = &parent["assetIndex"];
+    let asset_index_id = asset_index["id"].as_str().ok_or(LaunchError::DataInvalid)?;
+    let index_dir = game_path.to_string() + "/assets/indexes/";
+    let index_path = index_dir.clone() + asset_index_id + ".json";
+    report.check(&ExpectedFile::new(index_path.clone(), asset_index))?;
+    if !exists(&index_path)? {
+        if !exists(&index_dir)? {
+            create_dir_all(&index_dir)?;
+        }
+        let url = asset_index["url"]
+            .as_str()
+            .ok_or(LaunchError::DataInvalid)?
+            .to_string();
+        download(url, index_path.clone(), 3).await?;
+    }
+
+    // assets，文件名即为sha1
+    let objects_dir = game_path.to_string() + "/assets/objects/";
+    let index = serde_json::from_str::<Value>(&read_to_string(&index_path)?)?;
+    for node in index["objects"]
+        .as_object()
+        .ok_or(LaunchError::DataInvalid)?
+        .values()
+    {
+        let hash = node["hash"].as_str().ok_or(LaunchError::DataInvalid)?;
+        if hash.len() < 2 {
+            return Err(LaunchError::DataInvalid);
+        }
+        report.check(&ExpectedFile {
+            path: objects_dir.clone() + &hash[0..2] + "/" + hash,
+            sha1: Some(hash.to_string()),
+            size: node["size"].as_u64(),
+        })?;
+    }
+    tasks.append(&mut download_assets(
+        game_path,
+        asset_index_id,
+        "{assets_source}",
+        &game.get_game_dir(game_path),
+    )?);
+
+    info!("Repair {}: {report}", game.version);
+    Ok((report, tasks))
+}
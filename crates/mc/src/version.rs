@@ -1,11 +1,18 @@
 use serde_json::json;
+use std::collections::BTreeMap;
 
 /// Minecraft Installation
 #[derive(Clone)]
 pub struct MCInstallation {
+    /// 根据系统内存和mod数量自动设置xms和xmx
+    pub auto_memory: bool,
+
     /// 备注
     pub description: String,
 
+    /// 额外的环境变量
+    pub env: BTreeMap<String, String>,
+
     /// MC自定义参数
     pub game_args: Vec<String>,
 
@@ -15,12 +22,24 @@ pub struct MCInstallation {
     /// 窗口高度
     pub height: u32,
 
+    /// 受管理的Java的id，为空时使用java_path
+    pub java_id: String,
+
     /// java可执行文件路径
     pub java_path: String,
 
     /// JVM自定义参数
     pub jvm_args: Vec<String>,
 
+    /// JVM参数预设名
+    pub jvm_preset: String,
+
+    /// 游戏退出后执行的命令
+    pub post_exit: Vec<String>,
+
+    /// 启动前执行的命令，失败时取消启动
+    pub pre_launch: Vec<String>,
+
     /// 版本隔离
     pub separated: bool,
 
@@ -30,8 +49,9 @@ pub struct MCInstallation {
     /// 窗口宽度
     pub width: u32,
 
-    /// 封装器
-    pub wrapper: String,
+    /// 封装器链，按顺序嵌套，每项为一条命令及其参数
+    /// 例如[["gamemoderun"], ["mangohud", "--dlsym"]]
+    pub wrapper: Vec<Vec<String>>,
 
     /// xms参数
     pub xms: String,
@@ -40,6 +60,68 @@ pub struct MCInstallation {
     pub xmx: String,
 }
 
+impl MCInstallation {
+    /// 游戏目录，开启版本隔离时为版本文件夹
+    pub fn get_game_dir(&self, game_path: &str) -> String {
+        if self.separated {
+            game_path.to_string() + "/versions/" + &self.version
+        } else {
+            game_path.to_string()
+        }
+    }
+
+    /// mods文件夹中的mod数量
+    pub fn count_mods(&self, game_path: &str) -> usize {
+        let Ok(entries) = std::fs::read_dir(self.get_game_dir(game_path) + "/mods") else {
+            return 0;
+        };
+        entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().ends_with(".jar"))
+            .count()
+    }
+
+    /// 是否安装了mod加载器，即版本json中有inheritsFrom
+    pub fn has_mod_loader(&self, game_path: &str) -> bool {
+        let path = format!("{game_path}/versions/{0}/{0}.json", self.version);
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok())
+            .is_some_and(|json| json["inheritsFrom"].is_string())
+    }
+
+    /// 版本json中的javaVersion，mod加载器的json中没有时读取继承的版本
+    fn read_java_version(&self, game_path: &str) -> Option<serde_json::Value> {
+        let mut version = self.version.clone();
+        // 继承最多只有一层，限制次数以防循环继承
+        for _ in 0..2 {
+            let path = format!("{game_path}/versions/{0}/{0}.json", version);
+            let json =
+                serde_json::from_str::<serde_json::Value>(&std::fs::read_to_string(path).ok()?)
+                    .ok()?;
+            if json["javaVersion"].is_object() {
+                return Some(json["javaVersion"].clone());
+            }
+            version = json["inheritsFrom"].as_str()?.to_string();
+        }
+        None
+    }
+
+    /// 需要的Java主版本，版本json中未指定时为None
+    pub fn get_java_requirement(&self, game_path: &str) -> Option<u8> {
+        self.read_java_version(game_path)?["majorVersion"]
+            .as_u64()
+            .map(|v| v as u8)
+    }
+
+    /// Mojang提供的Java运行时名称，如java-runtime-delta
+    pub fn get_java_component(&self, game_path: &str) -> Option<String> {
+        self.read_java_version(game_path)?["component"]
+            .as_str()
+            .map(|s| s.to_string())
+    }
+}
+
 #[derive(Clone, PartialEq, Eq)]
 pub enum MCType {
     Release,
@@ -1,6 +1,11 @@
 //! utils
 
+pub mod memory;
+pub mod shell;
+pub mod time;
+
 use log::{info, warn};
+use sha1::{Digest, Sha1};
 use std::env::consts as env;
 use std::fs;
 use std::io::ErrorKind;
@@ -80,6 +85,24 @@ pub async fn download(url: String, path: String, max: usize) -> Result<(), DLErr
     Ok(())
 }
 
+/// 计算文件的sha1
+pub fn get_sha1(path: &str) -> std::io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha1::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// 计算字符串的sha1
+pub fn get_str_sha1(s: &str) -> String {
+    format!("{:x}", Sha1::digest(s.as_bytes()))
+}
+
+/// 检查文件的sha1是否与给定值一致
+pub fn check_sha1(path: &str, sha1: &str) -> std::io::Result<bool> {
+    Ok(get_sha1(path)?.eq_ignore_ascii_case(sha1))
+}
+
 /// 获取文件所在文件夹
 pub fn get_parent_dir(path: &str) -> String {
     let mut vec: Vec<&str> = path.split("/").collect();
@@ -175,3 +198,23 @@ pub async fn list_file_async(path: &String) -> tokio::io::Result<Vec<String>> {
     }
     Ok(result)
 }
+
+/// 在PATH中查找可执行文件，返回其完整路径
+pub fn find_in_path(name: &str) -> Option<String> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join(name))
+        .find(|path| {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                path.metadata()
+                    .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+            }
+            #[cfg(not(unix))]
+            {
+                path.is_file()
+            }
+        })
+        .map(|path| path.to_string_lossy().to_string())
+}
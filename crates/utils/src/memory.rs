@@ -0,0 +1,35 @@
+//! 系统内存
+
+/// 物理内存（字节）
+#[derive(Clone, Copy)]
+pub struct MemoryInfo {
+    pub total: u64,
+    /// 可用内存，包括可回收的缓存
+    pub available: u64,
+}
+
+/// 获取物理内存，目前仅支持Linux
+#[cfg(target_os = "linux")]
+pub fn get_memory_info() -> Option<MemoryInfo> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    // 每行形如 "MemTotal:       16318432 kB"
+    let read = |key: &str| -> Option<u64> {
+        let line = meminfo.lines().find(|line| line.starts_with(key))?;
+        let kb: u64 = line[key.len()..]
+            .trim()
+            .trim_end_matches("kB")
+            .trim()
+            .parse()
+            .ok()?;
+        Some(kb * 1024)
+    };
+    Some(MemoryInfo {
+        total: read("MemTotal:")?,
+        available: read("MemAvailable:")?,
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get_memory_info() -> Option<MemoryInfo> {
+    None
+}
@@ -0,0 +1,174 @@
+//! POSIX shell相关
+
+/// 不需要加引号的字符
+fn is_safe(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "%+,-./:=@_".contains(c)
+}
+
+/// 为参数加上引号，使其可以原样传入POSIX shell
+pub fn quote(arg: &str) -> String {
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        return arg.to_string();
+    }
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// 将参数列表拼接为一条shell命令
+pub fn join(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| quote(arg))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// 解析shell命令时的错误
+#[derive(Debug, PartialEq, Eq)]
+pub enum SplitError {
+    /// 引号未闭合
+    UnclosedQuote,
+    /// 末尾为转义符
+    TrailingBackslash,
+}
+
+impl std::fmt::Display for SplitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SplitError::UnclosedQuote => write!(f, "Unclosed quote."),
+            SplitError::TrailingBackslash => write!(f, "Trailing backslash."),
+        }
+    }
+}
+
+/// 按POSIX shell的规则将命令拆分为参数列表，不展开变量
+pub fn split(s: &str) -> Result<Vec<String>, SplitError> {
+    let mut args = Vec::new();
+    let mut arg = String::new();
+    // 当前参数是否已开始，用于保留空字符串参数
+    let mut in_arg = false;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' | '\n' => {
+                if in_arg {
+                    args.push(std::mem::take(&mut arg));
+                    in_arg = false;
+                }
+            }
+            '\\' => {
+                in_arg = true;
+                match chars.next() {
+                    // 转义的换行符表示续行
+                    Some('\n') => {}
+                    Some(c) => arg.push(c),
+                    None => return Err(SplitError::TrailingBackslash),
+                }
+            }
+            '\'' => {
+                in_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => arg.push(c),
+                        None => return Err(SplitError::UnclosedQuote),
+                    }
+                }
+            }
+            '"' => {
+                in_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        // 双引号中只有这些字符可以转义
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => arg.push(c),
+                            Some('\n') => {}
+                            Some(c) => {
+                                arg.push('\\');
+                                arg.push(c);
+                            }
+                            None => return Err(SplitError::UnclosedQuote),
+                        },
+                        Some(c) => arg.push(c),
+                        None => return Err(SplitError::UnclosedQuote),
+                    }
+                }
+            }
+            c => {
+                in_arg = true;
+                arg.push(c);
+            }
+        }
+    }
+    if in_arg {
+        args.push(arg);
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn quote_arg() {
+        assert_eq!(quote("abc-1.0/x=y"), "abc-1.0/x=y");
+        assert_eq!(quote(""), "''");
+        assert_eq!(quote("a b"), "'a b'");
+        assert_eq!(quote("it's"), "'it'\\''s'");
+    }
+
+    #[test]
+    fn split_nested_quotes() {
+        assert_eq!(
+            split(r#"echo "it's" 'say "hi"' "a'b'c""#),
+            Ok(args(&["echo", "it's", r#"say "hi""#, "a'b'c"]))
+        );
+        assert_eq!(split(r#"a"b c"'d e'f"#), Ok(args(&["ab cd ef"])));
+    }
+
+    #[test]
+    fn split_backslash() {
+        assert_eq!(split(r"a\ b c\\d"), Ok(args(&["a b", r"c\d"])));
+        assert_eq!(
+            split(r#""\"\$\`\\\n" '\n'"#),
+            Ok(args(&[r#""$`\\n"#, r"\n"]))
+        );
+        assert_eq!(split("a\\\nb"), Ok(args(&["ab"])));
+    }
+
+    #[test]
+    fn split_empty() {
+        assert_eq!(split(""), Ok(Vec::new()));
+        assert_eq!(split("  \t\n "), Ok(Vec::new()));
+        assert_eq!(split(r#"'' """#), Ok(args(&["", ""])));
+        assert_eq!(split("a '' b"), Ok(args(&["a", "", "b"])));
+    }
+
+    #[test]
+    fn split_error() {
+        assert_eq!(split("echo 'abc"), Err(SplitError::UnclosedQuote));
+        assert_eq!(split(r#"echo "abc"#), Err(SplitError::UnclosedQuote));
+        assert_eq!(split(r#"echo "abc\"#), Err(SplitError::UnclosedQuote));
+        assert_eq!(split(r"echo abc\"), Err(SplitError::TrailingBackslash));
+    }
+
+    #[test]
+    fn split_join() {
+        let list = args(&[
+            "java",
+            "",
+            "a b",
+            "it's",
+            r#"say "hi""#,
+            r"C:\Program Files\",
+            "$HOME `pwd`",
+            "line\nbreak",
+            "中文 路径",
+        ]);
+        assert_eq!(split(&join(&list)), Ok(list));
+    }
+}
@@ -0,0 +1,33 @@
+//! 时间格式化
+
+use chrono::{DateTime, Local};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 当前的unix时间戳（秒）
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 将unix时间戳格式化为本地时间，如2025-01-01 12:00
+pub fn format_time(secs: u64) -> String {
+    match DateTime::from_timestamp(secs as i64, 0) {
+        Some(time) => time
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M")
+            .to_string(),
+        None => String::new(),
+    }
+}
+
+/// 格式化时长，如1h 23m
+pub fn format_duration(secs: u64) -> String {
+    let (hours, minutes) = (secs / 3600, secs % 3600 / 60);
+    if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else {
+        format!("{minutes}m")
+    }
+}